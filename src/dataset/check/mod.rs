@@ -0,0 +1,248 @@
+use super::tablets::{list_tablets, read_lines, sort_lines, write_marker, SORTED};
+use super::transaction::Transaction;
+use crate::{line::Line, Branch, Dataset, Error, Result, Schema, Trunks};
+use async_stream::try_stream;
+use futures_core::stream::Stream;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+// unknown tablets are moved here by a fix instead of being deleted
+const QUARANTINE_DIR: &str = ".csvs/quarantine";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum TabletProblem {
+    /// line appears in the tablet more than once
+    Duplicate { tablet: String, line: Line },
+    /// lines of the tablet are not sorted by key and value
+    Unsorted { tablet: String },
+    /// tablet filename is not a trunk-leaf pair of the schema,
+    /// a fix moves it to the quarantine directory
+    Unknown { tablet: String },
+    /// key of the line is not a value of any trunk tablet
+    Dangling { tablet: String, line: Line },
+}
+
+impl fmt::Display for TabletProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TabletProblem::Duplicate { tablet, line } => {
+                write!(
+                    f,
+                    "duplicate line in {}: {},{}",
+                    tablet, line.key, line.value
+                )
+            }
+            TabletProblem::Unsorted { tablet } => write!(f, "unsorted tablet {}", tablet),
+            TabletProblem::Unknown { tablet } => write!(f, "tablet {} is not in schema", tablet),
+            TabletProblem::Dangling { tablet, line } => {
+                write!(
+                    f,
+                    "dangling line in {}: {},{}",
+                    tablet, line.key, line.value
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Tablet {
+    pub filename: String,
+    pub trunk: String,
+    pub branch: String,
+}
+
+// no lines leaves nothing staged, which removes the tablet
+fn stage_lines(transaction: &mut Transaction, filename: &str, lines: &[Line]) -> Result<()> {
+    let staged = transaction.stage(filename);
+
+    if !lines.is_empty() {
+        let mut wtr = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_path(&staged)?;
+
+        for line in lines {
+            wtr.serialize(line)?;
+        }
+
+        wtr.flush()?;
+    }
+
+    Ok(())
+}
+
+// keep the lines of a tablet out of the dataset without losing them
+fn stage_quarantine(transaction: &mut Transaction, filename: &str) -> Result<()> {
+    let quarantined = transaction.stage(&format!("{}/{}", QUARANTINE_DIR, filename));
+
+    fs::create_dir_all(
+        quarantined
+            .parent()
+            .expect("quarantine should have a parent"),
+    )?;
+
+    fs::copy(transaction.current(filename), quarantined)?;
+
+    // nothing staged for the tablet removes it
+    transaction.stage(filename);
+
+    Ok(())
+}
+
+// tablet as the fix so far would leave it
+fn current(transaction: &Option<Transaction>, dir: &Path, filename: &str) -> PathBuf {
+    match transaction {
+        Some(t) => t.current(filename),
+        None => dir.join(filename),
+    }
+}
+
+// trunks first, so that fixing a trunk tablet
+// also finds lines left dangling in its leaf tablets
+fn plan_check(schema: &Schema) -> Vec<Tablet> {
+    let mut branches: Vec<String> = schema.0.keys().cloned().collect();
+
+    branches.sort_by(schema.clone().sort_nesting_descending());
+
+    branches.iter().fold(vec![], |with_branch, branch| {
+        let mut leaves = schema.0[branch].leaves.0.clone();
+
+        leaves.sort();
+
+        let tablets_new = leaves
+            .iter()
+            .map(|leaf| Tablet {
+                filename: format!("{}-{}.csv", branch, leaf),
+                trunk: branch.to_owned(),
+                branch: leaf.to_owned(),
+            })
+            .collect();
+
+        [with_branch, tablets_new].concat()
+    })
+}
+
+// values of a branch found in all tablets of its trunks
+fn branch_values(
+    transaction: &Option<Transaction>,
+    dir: &Path,
+    schema: &Schema,
+    branch: &str,
+) -> Result<Option<HashSet<String>>> {
+    let trunks = match schema.0.get(branch) {
+        None => vec![],
+        Some(Branch {
+            trunks: Trunks(ts), ..
+        }) => ts.to_vec(),
+    };
+
+    // values of a root are not referenced by any tablet
+    if trunks.is_empty() {
+        return Ok(None);
+    }
+
+    let mut values = HashSet::new();
+
+    for trunk in trunks {
+        let lines = read_lines(&current(
+            transaction,
+            dir,
+            &format!("{}-{}.csv", trunk, branch),
+        ))?;
+
+        values.extend(lines.into_iter().map(|line| line.value));
+    }
+
+    Ok(Some(values))
+}
+
+pub fn check_dataset(dataset: Dataset, fix: bool) -> impl Stream<Item = Result<TabletProblem>> {
+    try_stream! {
+        let schema = dataset.schema().await?;
+
+        let strategy = plan_check(&schema);
+
+        let known: HashSet<String> = strategy.iter().map(|tablet| tablet.filename.to_owned()).collect();
+
+        // every repair is staged and committed together after the scan
+        let mut transaction = if fix { Some(Transaction::begin(&dataset.dir)?) } else { None };
+
+        for filename in list_tablets(&dataset.dir)? {
+            if !known.contains(&filename) {
+                yield TabletProblem::Unknown { tablet: filename.to_owned() };
+
+                if let Some(t) = transaction.as_mut() {
+                    stage_quarantine(t, &filename)?;
+                }
+            }
+        }
+
+        for tablet in strategy {
+            let filepath = current(&transaction, &dataset.dir, &tablet.filename);
+
+            if fs::metadata(&filepath).is_err() {
+                continue;
+            }
+
+            let lines = read_lines(&filepath)?;
+
+            let referenced = branch_values(&transaction, &dataset.dir, &schema, &tablet.trunk)?;
+
+            let mut seen = HashSet::new();
+
+            let mut lines_new = vec![];
+
+            for line in lines.iter() {
+                let is_duplicate = !seen.insert((line.key.to_owned(), line.value.to_owned()));
+
+                if is_duplicate {
+                    yield TabletProblem::Duplicate { tablet: tablet.filename.to_owned(), line: line.clone() };
+
+                    continue;
+                }
+
+                let is_dangling = match &referenced {
+                    None => false,
+                    Some(vs) => !vs.contains(&line.key),
+                };
+
+                if is_dangling {
+                    yield TabletProblem::Dangling { tablet: tablet.filename.to_owned(), line: line.clone() };
+
+                    continue;
+                }
+
+                lines_new.push(line.clone());
+            }
+
+            let is_sorted = lines.windows(2).all(|pair| {
+                (&pair[0].key, &pair[0].value) <= (&pair[1].key, &pair[1].value)
+            });
+
+            if !is_sorted {
+                yield TabletProblem::Unsorted { tablet: tablet.filename.to_owned() };
+            }
+
+            let is_changed = !is_sorted || lines_new.len() != lines.len();
+
+            if let (Some(t), true) = (transaction.as_mut(), is_changed) {
+                sort_lines(&mut lines_new);
+
+                stage_lines(t, &tablet.filename, &lines_new)?;
+            }
+        }
+
+        if let Some(mut t) = transaction {
+            // a fixed dataset is sorted, so reads can bisect its tablets
+            if fs::metadata(dataset.dir.join(SORTED)).is_err() {
+                write_marker(&t.stage(SORTED))?;
+            }
+
+            t.commit()?;
+        }
+    }
+}
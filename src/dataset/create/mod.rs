@@ -1,10 +1,47 @@
-use crate::{Dataset, Result};
+use super::tablets::{write_marker, SORTED};
+use crate::version::write_version;
+use crate::{Dataset, Entry, Error, Result};
+use std::fs;
 use std::path::PathBuf;
 
-pub async fn create_dataset(dataset: &Dataset, name: &str) -> Result<()> {
+pub async fn create_dataset(dataset: &Dataset, name: &str, schema: Option<Entry>) -> Result<Dataset> {
+    let dir: PathBuf = dataset.dir.join(name);
+
     // check that path/name doesn't exist
+    if fs::metadata(&dir).is_ok() {
+        return Err(Error::from_message(format!(
+            "dataset {} already exists",
+            dir.display()
+        )));
+    }
+
+    let is_schema = match &schema {
+        None => true,
+        Some(s) => s.base == "_",
+    };
+
+    if !is_schema {
+        return Err(Error::invalid_query("$._", "schema base is not _"));
+    }
+
     // create directory name
-    // write .csvs.csv
+    fs::create_dir_all(&dir)?;
+
+    write_version(&dir.join(".csvs.csv"))?;
+
+    // an empty dataset is sorted, every write keeps it so
+    fs::create_dir_all(dir.join(".csvs"))?;
+
+    write_marker(&dir.join(SORTED))?;
+
+    let dataset_new = Dataset::new(&dir);
+
     // write schema
-    Ok(())
+    // an empty schema leaves no _-_.csv, same as any empty tablet
+    match schema {
+        None => (),
+        Some(s) => dataset_new.clone().update_record(vec![s]).await?,
+    }
+
+    Dataset::open(&dir).await
 }
@@ -1,33 +1,41 @@
-use crate::{Branch, Leaves, Schema, Trunks, line::Line, Entry, Error, Result, Dataset};
-use async_stream::{stream, try_stream};
+use super::tablets::read_lines;
+use super::transaction::Transaction;
+use crate::{line::Line, Branch, Dataset, Entry, Error, Leaves, Matcher, Result, Schema, Trunks};
+use async_stream::try_stream;
 use futures_core::stream::Stream;
 use futures_util::pin_mut;
 use futures_util::stream::StreamExt;
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::fs::File;
-use std::io::prelude::*;
-use std::path::{Path, PathBuf};
-use temp_dir::TempDir;
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Cascade {
+    /// remove only the lines that have the base value
+    None,
+    /// also remove descendant values that no other trunk value references
+    Exclusive,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct Tablet {
     pub filename: String,
+    pub thing: String,
     pub trait_: String,
     pub trait_is_first: bool,
 }
 
 fn plan_delete(schema: &Schema, query: &Entry) -> Result<Vec<Tablet>> {
     let (trunks, leaves) = match schema.0.get(&query.base) {
-        None => (vec![], vec![]),
+        None => return Err(Error::unknown_branch(&query.base)),
         Some(Branch {
             trunks: Trunks(ts),
             leaves: Leaves(ls),
+            ..
         }) => (ts.to_vec(), ls.to_vec()),
     };
 
     let base_value = match &query.base_value {
-        None => return Err(Error::from_message("unexpected missing option")),
+        None => return Err(Error::missing_base_value(&query.base)),
         Some(v) => v,
     };
 
@@ -35,6 +43,7 @@ fn plan_delete(schema: &Schema, query: &Entry) -> Result<Vec<Tablet>> {
         .iter()
         .map(|trunk| Tablet {
             filename: format!("{}-{}.csv", trunk, query.base),
+            thing: trunk.to_owned(),
             trait_: base_value.to_owned(),
             trait_is_first: false,
         })
@@ -44,6 +53,7 @@ fn plan_delete(schema: &Schema, query: &Entry) -> Result<Vec<Tablet>> {
         .iter()
         .map(|leaf| Tablet {
             filename: format!("{}-{}.csv", query.base, leaf),
+            thing: leaf.to_owned(),
             trait_: base_value.to_owned(),
             trait_is_first: true,
         })
@@ -52,117 +62,235 @@ fn plan_delete(schema: &Schema, query: &Entry) -> Result<Vec<Tablet>> {
     Ok([trunk_tablets, leaf_tablets].concat())
 }
 
-pub async fn delete_tablet(path: PathBuf, tablet: Tablet) -> Result<()> {
-    let filepath = path.join(&tablet.filename);
+// returns removed lines, reads the tablet as staged by earlier deletes of the transaction
+fn delete_tablet(transaction: &mut Transaction, tablet: &Tablet) -> Result<Vec<Line>> {
+    let lines = read_lines(&transaction.current(&tablet.filename))?;
 
-    match fs::metadata(&filepath) {
-        Err(_) => return Ok(()),
-        Ok(m) => {
-            if m.len() == 0 {
-                return Ok(());
-            }
-        }
+    let (removed, kept): (Vec<Line>, Vec<Line>) = lines.into_iter().partition(|line| {
+        let trait_ = if tablet.trait_is_first {
+            &line.key
+        } else {
+            &line.value
+        };
+
+        trait_ == &tablet.trait_
+    });
+
+    // leave tablets without the value as they are
+    if removed.is_empty() {
+        return Ok(removed);
     }
 
-    let file = File::open(&filepath)?;
+    let staged = transaction.stage(&tablet.filename);
 
-    let mut rdr = csv::ReaderBuilder::new()
+    let mut wtr = csv::WriterBuilder::new()
         .has_headers(false)
-        .from_reader(file);
+        .from_path(&staged)?;
+
+    for line in kept {
+        wtr.serialize(line)?;
+    }
 
-    let temp_path = TempDir::new()?;
+    wtr.flush()?;
 
-    let filename = match filepath.file_name() {
-        None => return Err(Error::from_message("unexpected missing filename")),
-        Some(s) => s,
+    Ok(removed)
+}
+
+// a value is referenced while any trunk tablet has it, staged or not
+fn is_referenced(
+    transaction: &Transaction,
+    schema: &Schema,
+    branch: &str,
+    value: &str,
+) -> Result<bool> {
+    let trunks = match schema.0.get(branch) {
+        None => vec![],
+        Some(Branch {
+            trunks: Trunks(ts), ..
+        }) => ts.to_vec(),
     };
 
-    let output = temp_path.as_ref().join(filename);
+    for trunk in trunks {
+        let lines = read_lines(&transaction.current(&format!("{}-{}.csv", trunk, branch)))?;
 
-    let temp_file = File::create(&output)?;
+        if lines.iter().any(|line| line.value == value) {
+            return Ok(true);
+        }
+    }
 
-    let mut wtr = csv::WriterBuilder::new()
-        .has_headers(false)
-        .flexible(true)
-        .from_writer(temp_file);
-
-    for result in rdr.records() {
-        let record = result?;
-
-        let line = Line {
-            key: match record.get(0) {
-                None => String::from(""),
-                Some(s) => s.to_owned(),
-            },
-            value: match record.get(1) {
-                None => String::from(""),
-                Some(s) => s.to_owned(),
-            },
-        };
+    Ok(false)
+}
 
-        let trait_ = if tablet.trait_is_first {
-            line.key.to_owned()
-        } else {
-            line.value.to_owned()
-        };
+// delete the base value of the query and return what was removed from leaf tablets
+fn delete_entry(schema: &Schema, transaction: &mut Transaction, query: &Entry) -> Result<Entry> {
+    let strategy = plan_delete(schema, query)?;
+
+    let mut removed = Entry {
+        base: query.base.to_owned(),
+        base_value: query.base_value.clone(),
+        base_matcher: query.base_matcher.clone(),
+        leader_value: None,
+        leaves: HashMap::new(),
+    };
 
-        let is_match = trait_ == tablet.trait_;
+    for tablet in strategy {
+        let lines = delete_tablet(transaction, &tablet)?;
 
-        if !is_match {
-            wtr.serialize(line)?;
+        if !tablet.trait_is_first {
+            continue;
+        }
+
+        let items: Vec<Entry> = lines
+            .into_iter()
+            .map(|line| Entry {
+                base: tablet.thing.to_owned(),
+                base_value: Some(line.value),
+                base_matcher: None,
+                leader_value: None,
+                leaves: HashMap::new(),
+            })
+            .collect();
+
+        if !items.is_empty() {
+            removed.leaves.insert(tablet.thing.to_owned(), items);
         }
     }
 
-    wtr.flush()?;
+    Ok(removed)
+}
 
-    // if empty
-    match fs::metadata(&output) {
-        Err(_) => fs::remove_file(filepath)?,
-        Ok(m) => {
-            if m.len() == 0 {
-                fs::remove_file(filepath)?;
-            } else {
-                fs::copy(output, filepath)?;
-            }
-        }
+// entry of the only value a query without leaves can match,
+// a bare base value as it is in the tablets, or an $eq or a regex like ^value1$
+fn literal_target(query: &Entry) -> Option<Entry> {
+    if !query.leaves.is_empty() {
+        return None;
     }
 
-    Ok(())
+    let value = match &query.base_matcher {
+        None => query.base_value.clone()?,
+        Some(matcher) if matcher.0.len() == 1 => matcher.literal()?,
+        Some(_) => return None,
+    };
+
+    Some(Entry {
+        base_value: Some(value),
+        base_matcher: None,
+        ..query.clone()
+    })
+}
+
+// a select reads a bare value as a regex, a delete only ever takes it literally
+fn literal_query(query: Entry) -> Entry {
+    match (&query.base_matcher, &query.base_value) {
+        (None, Some(value)) => Entry {
+            base_matcher: Some(Matcher::eq(value)),
+            ..query
+        },
+        _ => query,
+    }
 }
 
 pub fn delete_record_stream<S: Stream<Item = Result<Entry>>>(
     dataset: Dataset,
     input: S,
+    cascade: Cascade,
 ) -> impl Stream<Item = Result<Entry>> {
     try_stream! {
-        let schema = dataset.clone().select_schema().await?;
+        let schema = dataset.schema().await?;
+
+        // the whole input commits at once, a failed query leaves the dataset as it was
+        let mut transaction = dataset.begin()?;
 
         for await query in input {
             let query = query?;
 
-            let strategy = plan_delete(&schema, &query)?;
+            // delete every entry that a select would find,
+            // a query that can only match one value skips the select
+            let targets = match literal_target(&query) {
+                Some(target) => vec![target],
+                None => dataset.clone().select_record(vec![literal_query(query)]).await?,
+            };
+
+            for target in targets {
+                let removed = delete_entry(&schema, &mut transaction, &target)?;
+
+                yield target.clone();
 
-            for tablet in strategy {
-                delete_tablet(dataset.dir.clone(), tablet).await?;
+                if cascade == Cascade::None {
+                    continue;
+                }
+
+                // descendants go in the same commit as their trunk
+                for entry in delete_descendants(&schema, &mut transaction, &target.base, removed)? {
+                    yield entry;
+                }
             }
+        }
 
-            yield query;
+        transaction.commit()?;
+    }
+}
+
+// values below the base that lost their last trunk with the removed lines,
+// each with the lines removed from its leaves
+fn delete_descendants(
+    schema: &Schema,
+    transaction: &mut Transaction,
+    base: &str,
+    removed: Entry,
+) -> Result<Vec<Entry>> {
+    let crown = schema.find_crown(base);
+
+    let mut descendants = vec![];
+
+    // descendant values that might have lost their last trunk
+    let mut candidates: Vec<Entry> = removed.leaves.into_values().flatten().collect();
+
+    while let Some(candidate) = candidates.pop() {
+        let value = match &candidate.base_value {
+            None => continue,
+            Some(v) => v.to_owned(),
+        };
+
+        // values without leaves have nothing left to remove
+        let is_trunk = schema.count_leaves(&candidate.base) > 0;
+
+        if !is_trunk || !crown.contains(&candidate.base) {
+            continue;
+        }
+
+        if is_referenced(transaction, schema, &candidate.base, &value)? {
+            continue;
         }
+
+        let removed = delete_entry(schema, transaction, &candidate)?;
+
+        if removed.leaves.is_empty() {
+            continue;
+        }
+
+        candidates.extend(removed.leaves.clone().into_values().flatten());
+
+        descendants.push(removed);
     }
+
+    Ok(descendants)
 }
 
-pub async fn delete_record(dataset: Dataset, query: Vec<Entry>) -> Result<()> {
+pub async fn delete_record(dataset: Dataset, query: Vec<Entry>, cascade: Cascade) -> Result<()> {
     let readable_stream = try_stream! {
         for q in query {
             yield q;
         }
     };
 
-    let s = dataset.delete_record_stream(readable_stream);
+    let s = dataset.delete_record_stream(readable_stream, cascade);
 
     pin_mut!(s); // needed for iteration
 
-    while let Some(entry) = s.next().await {}
+    while let Some(entry) = s.next().await {
+        entry?;
+    }
 
     Ok(())
 }
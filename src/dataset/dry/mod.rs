@@ -0,0 +1,99 @@
+use super::tablets::read_lines;
+use crate::{line::Line, Dataset, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::future::Future;
+use std::path::Path;
+use temp_dir::TempDir;
+
+/// lines a mutation adds to and removes from one tablet
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TabletChange {
+    pub tablet: String,
+    pub added: Vec<Line>,
+    pub removed: Vec<Line>,
+}
+
+/// tablet edits of a mutation, sorted by tablet filename
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct Changeset(pub Vec<TabletChange>);
+
+// lines of a that are not in b, counting duplicates
+fn subtract(a: &[Line], b: &[Line]) -> Vec<Line> {
+    let mut counts: HashMap<(&str, &str), usize> = HashMap::new();
+
+    for line in b {
+        *counts.entry((&line.key, &line.value)).or_default() += 1;
+    }
+
+    a.iter()
+        .filter(
+            |line| match counts.get_mut(&(line.key.as_str(), line.value.as_str())) {
+                Some(n) if *n > 0 => {
+                    *n -= 1;
+
+                    false
+                }
+                _ => true,
+            },
+        )
+        .cloned()
+        .collect()
+}
+
+// lines of each tablet that the dry run edited, compared with the dataset
+fn diff_dry(dir: &Path, dry: &Path) -> Result<Changeset> {
+    let mut filenames = vec![];
+
+    for file_entry in fs::read_dir(dry)? {
+        let file_entry = file_entry?;
+
+        if file_entry.file_type()?.is_file() {
+            filenames.push(file_entry.file_name().to_string_lossy().to_string());
+        }
+    }
+
+    filenames.sort();
+
+    let mut changes = vec![];
+
+    for filename in filenames {
+        let lines_before = read_lines(&dir.join(&filename))?;
+
+        let lines_after = read_lines(&dry.join(&filename))?;
+
+        let added = subtract(&lines_after, &lines_before);
+
+        let removed = subtract(&lines_before, &lines_after);
+
+        if !added.is_empty() || !removed.is_empty() {
+            changes.push(TabletChange {
+                tablet: filename,
+                added,
+                removed,
+            });
+        }
+    }
+
+    Ok(Changeset(changes))
+}
+
+// run the mutation with transactions that commit the tablets they stage
+// to a directory of edited tablets, and compare those with the dataset
+pub async fn dry_run<F, Fut>(dataset: Dataset, mutation: F) -> Result<Changeset>
+where
+    F: FnOnce(Dataset) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let dry_path = TempDir::new()?;
+
+    let dataset_dry = Dataset {
+        dry: Some(dry_path.path().to_owned()),
+        ..dataset.clone()
+    };
+
+    mutation(dataset_dry).await?;
+
+    diff_dry(&dataset.dir, dry_path.path())
+}
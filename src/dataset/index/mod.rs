@@ -0,0 +1,178 @@
+use crate::matcher::tokenize;
+use crate::{line::Line, Dataset, Error, Result};
+use super::transaction::Transaction;
+use super::tablets::{list_tablets, read_lines, sort_lines};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// reverse indexes live next to the journal, one per tablet
+pub const INDEX_DIR: &str = ".csvs/index";
+
+/// text indexes of leaf words, one per tablet
+pub const TEXT_DIR: &str = ".csvs/text";
+
+/// path of the reverse index inside the dataset, relative to the dataset directory
+pub fn index_name(filename: &str) -> String {
+    format!("{}/{}", INDEX_DIR, filename)
+}
+
+/// path of the text index inside the dataset, relative to the dataset directory
+pub fn text_name(filename: &str) -> String {
+    format!("{}/{}", TEXT_DIR, filename)
+}
+
+/// path of the length of the tablet that an index was written from, next to the index
+pub fn stamp_name(index: &str) -> String {
+    format!("{}.len", index)
+}
+
+/// record the length of the tablet that an index is written from
+pub fn write_stamp(tablet_path: &Path, stamp_path: &Path) -> Result<()> {
+    fs::write(stamp_path, fs::metadata(tablet_path)?.len().to_string())?;
+
+    Ok(())
+}
+
+// write lines to an index in the order that select bisects on
+fn write_sorted(index_path: &Path, lines: Vec<Line>) -> Result<()> {
+    let mut lines = lines;
+
+    sort_lines(&mut lines);
+
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_path(index_path)?;
+
+    for line in lines {
+        wtr.serialize(line)?;
+    }
+
+    wtr.flush()?;
+
+    Ok(())
+}
+
+/// write value,key lines of a tablet sorted by value
+pub fn write_index(tablet_path: &Path, index_path: &Path) -> Result<()> {
+    let lines = read_lines(tablet_path)?
+        .into_iter()
+        .map(|line| Line {
+            key: line.value,
+            value: line.key,
+        })
+        .collect();
+
+    write_sorted(index_path, lines)
+}
+
+/// write word,key lines of a tablet sorted by word,
+/// one for each distinct normalized word of a value
+pub fn write_text_index(tablet_path: &Path, index_path: &Path) -> Result<()> {
+    let lines = read_lines(tablet_path)?
+        .into_iter()
+        .flat_map(|line| {
+            tokenize(&line.value).into_iter().map(move |token| Line {
+                key: token,
+                value: line.key.to_owned(),
+            })
+        })
+        .collect();
+
+    write_sorted(index_path, lines)
+}
+
+/// tablets that have a reverse index
+pub fn list_indexes(dir: &Path) -> Result<Vec<String>> {
+    let index_dir = dir.join(INDEX_DIR);
+
+    if fs::metadata(&index_dir).is_err() {
+        return Ok(vec![]);
+    }
+
+    let mut filenames = vec![];
+
+    for file_entry in fs::read_dir(index_dir)? {
+        let file_entry = file_entry?;
+
+        let filename = file_entry.file_name().to_string_lossy().to_string();
+
+        // stamps sit next to the indexes
+        if file_entry.file_type()?.is_file() && filename.ends_with(".csv") {
+            filenames.push(filename);
+        }
+    }
+
+    filenames.sort();
+
+    Ok(filenames)
+}
+
+/// index was written after the last change to its tablet
+pub fn is_fresh(dir: &Path, filename: &str) -> Result<bool> {
+    is_current(dir, &index_name(filename), filename)
+}
+
+/// text index was written after the last change to its tablet
+pub fn is_text_fresh(dir: &Path, filename: &str) -> Result<bool> {
+    is_current(dir, &text_name(filename), filename)
+}
+
+// a change can land within the modification time of the index,
+// so the tablet must also have the length the index was written from
+fn is_current(dir: &Path, index: &str, filename: &str) -> Result<bool> {
+    let (index_meta, tablet_meta) = match (fs::metadata(dir.join(index)), fs::metadata(dir.join(filename))) {
+        (Ok(i), Ok(t)) => (i, t),
+        _ => return Ok(false),
+    };
+
+    let stamp = fs::read_to_string(dir.join(stamp_name(index))).ok();
+
+    Ok(index_meta.modified()? >= tablet_meta.modified()? && stamp == Some(tablet_meta.len().to_string()))
+}
+
+// tablets of the dataset, or the given tablets if they all exist
+fn find_tablets(dir: &Path, filenames: Vec<String>) -> Result<Vec<String>> {
+    if filenames.is_empty() {
+        return list_tablets(dir);
+    }
+
+    for filename in filenames.iter() {
+        if fs::metadata(dir.join(filename)).is_err() {
+            return Err(Error::from_message(format!("tablet {} does not exist", filename)));
+        }
+    }
+
+    Ok(filenames)
+}
+
+pub fn build_index(dataset: Dataset, filenames: Vec<String>) -> Result<()> {
+    let filenames = find_tablets(&dataset.dir, filenames)?;
+
+    let mut transaction = Transaction::begin(&dataset.dir)?;
+
+    for filename in filenames {
+        let index = index_name(&filename);
+
+        write_index(&dataset.dir.join(&filename), &transaction.stage(&index))?;
+
+        write_stamp(&dataset.dir.join(&filename), &transaction.stage(&stamp_name(&index)))?;
+    }
+
+    transaction.commit()
+}
+
+pub fn build_text_index(dataset: Dataset, filenames: Vec<String>) -> Result<()> {
+    let filenames = find_tablets(&dataset.dir, filenames)?;
+
+    let mut transaction = Transaction::begin(&dataset.dir)?;
+
+    for filename in filenames {
+        let index = text_name(&filename);
+
+        write_text_index(&dataset.dir.join(&filename), &transaction.stage(&index))?;
+
+        write_stamp(&dataset.dir.join(&filename), &transaction.stage(&stamp_name(&index)))?;
+    }
+
+    transaction.commit()
+}
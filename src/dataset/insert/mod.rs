@@ -1,4 +1,5 @@
 use crate::{Entry, Grain, line::Line, Schema, Error, Result, Dataset};
+use super::tablets::sort_file;
 use async_stream::{stream, try_stream};
 use futures_core::stream::{BoxStream, Stream};
 use futures_util::pin_mut;
@@ -8,8 +9,6 @@ use std::fs;
 use std::fs::OpenOptions;
 use std::fs::{rename, File};
 use std::path::{Path, PathBuf};
-use temp_dir::TempDir;
-use text_file_sort::sort::Sort;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct Tablet {
@@ -18,29 +17,16 @@ struct Tablet {
     pub branch: String,
 }
 
-async fn sort_file(filepath: &Path) -> Result<()> {
-    let temp_path = TempDir::new()?;
-
-    let filename = match filepath.file_name() {
-        None => return Err(Error::from_message("unexpected missing filename")),
-        Some(s) => s,
-    };
-
-    let output = temp_path.as_ref().join(filename);
-
-    Sort::new(vec![filepath.to_path_buf()], output.to_path_buf()).sort();
-
-    rename(output, filepath)?;
-
-    Ok(())
-}
-
 fn plan_insert(schema: &Schema, query: &Entry) -> Result<Vec<Tablet>> {
+    if !schema.0.contains_key(&query.base) {
+        return Err(Error::unknown_branch(&query.base));
+    }
+
     let crown = schema.find_crown(&query.base);
 
     let tablets = crown.iter().try_fold(vec![], |with_branch, branch| {
         let node = match schema.0.get(branch) {
-            None => return Err(Error::from_message("unexpected missing branch")),
+            None => return Err(Error::unknown_branch(branch)),
             Some(vs) => vs,
         };
 
@@ -62,13 +48,11 @@ fn plan_insert(schema: &Schema, query: &Entry) -> Result<Vec<Tablet>> {
 }
 
 fn insert_tablet<S: Stream<Item = Result<Entry>>>(
-    path: PathBuf,
+    filepath: PathBuf,
     tablet: Tablet,
     input: S,
 ) -> impl Stream<Item = Result<Entry>> {
     try_stream! {
-        let filepath = path.join(&tablet.filename);
-
         // create file if it doesn't exist
         if fs::metadata(&filepath).is_err() {
             File::create(&filepath)?;
@@ -110,14 +94,17 @@ pub fn insert_record_stream<S: Stream<Item = Result<Entry>>>(
     input: S,
 ) -> impl Stream<Item = Result<Entry>> {
     try_stream! {
-        let schema = dataset.clone().select_schema().await?;
+        let schema = dataset.schema().await?;
 
-        let mut strategy = vec![];
+        // inserts only append, so the whole input commits at once
+        let mut transaction = dataset.begin()?;
 
         for await query in input {
             let query = query?;
 
-            strategy = plan_insert(&schema, &query)?;
+            let strategy = plan_insert(&schema, &query)?;
+
+            schema.validate_values(&query)?;
 
             let query_stream = try_stream! {
                 yield query;
@@ -126,7 +113,9 @@ pub fn insert_record_stream<S: Stream<Item = Result<Entry>>>(
             let mut stream: BoxStream<'static, Result<Entry>> = Box::pin(query_stream);
 
             for tablet in &strategy {
-                stream = Box::pin(insert_tablet(dataset.dir.clone(), tablet.clone(), stream));
+                let staged = transaction.stage_copy(&tablet.filename)?;
+
+                stream = Box::pin(insert_tablet(staged, tablet.clone(), stream));
             }
 
             for await entry in stream {
@@ -136,20 +125,16 @@ pub fn insert_record_stream<S: Stream<Item = Result<Entry>>>(
             }
         }
 
-        for tablet in strategy {
-            let filepath = dataset.dir.join(&tablet.filename);
-
-            match fs::metadata(&filepath) {
-                Err(_) => return,
-                Ok(m) => if m.len() == 0 {
-                    // remove file if it is empty
-                    fs::remove_file(filepath)?;
-                } else {
-                    sort_file(&filepath).await?;
+        for staged in transaction.staged() {
+            match fs::metadata(&staged) {
+                Err(_) => (),
+                Ok(m) => if m.len() > 0 {
+                    sort_file(&staged)?;
                 }
             }
-
         }
+
+        transaction.commit()?;
     }
 }
 
@@ -164,7 +149,9 @@ pub async fn insert_record(dataset: Dataset, query: Vec<Entry>) -> Result<()> {
 
     pin_mut!(s); // needed for iteration
 
-    while let Some(entry) = s.next().await {}
+    while let Some(entry) = s.next().await {
+        entry?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,353 @@
+use super::transaction::{self, Transaction};
+use super::tablets::{list_tablets, read_lines, read_tablet_order, sort_lines, write_marker, Order, SORTED};
+use crate::version::{check_version, read_version, write_version, FORMAT_VERSION};
+use crate::{line::Line, Dataset, Entry, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+// number of root entries read back to check the migration
+const MIGRATE_SAMPLE: usize = 10;
+
+// branch description in legacy metadir.json
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct LegacyBranch {
+    trunk: Option<String>,
+    dir: Option<String>,
+}
+
+// legacy layout keys every value by UUID
+// metadir/props/{dir}/index.csv has UUID,value lines
+// metadir/pairs/{trunk}-{branch}.csv has trunkUUID,branchUUID lines
+struct Legacy {
+    branches: HashMap<String, LegacyBranch>,
+    props: HashMap<String, HashMap<String, String>>,
+    pairs: HashMap<String, Vec<Line>>,
+}
+
+// legacy props are sometimes json-escaped inside the csv field
+fn unescape(value: &str) -> String {
+    if value.starts_with('"') {
+        if let Ok(s) = serde_json::from_str::<String>(value) {
+            return s;
+        }
+    }
+
+    value.to_owned()
+}
+
+fn read_legacy(dir: &Path) -> Result<Legacy> {
+    let metadir = File::open(dir.join("metadir.json"))?;
+
+    let branches: HashMap<String, LegacyBranch> = serde_json::from_reader(metadir)?;
+
+    let mut props = HashMap::new();
+
+    let mut pairs = HashMap::new();
+
+    for (branch, LegacyBranch { trunk, dir: prop_dir }) in branches.iter() {
+        let prop_dir = match prop_dir {
+            None => branch.to_owned(),
+            Some(d) => d.to_owned(),
+        };
+
+        if !props.contains_key(&prop_dir) {
+            let prop_path = dir.join("metadir").join("props").join(&prop_dir).join("index.csv");
+
+            let values: HashMap<String, String> = read_lines(&prop_path)?
+                .into_iter()
+                .map(|line| (line.key, unescape(&line.value)))
+                .collect();
+
+            props.insert(prop_dir, values);
+        }
+
+        if let Some(t) = trunk {
+            let pair_path = dir.join("metadir").join("pairs").join(format!("{}-{}.csv", t, branch));
+
+            pairs.insert(branch.to_owned(), read_lines(&pair_path)?);
+        }
+    }
+
+    Ok(Legacy {
+        branches,
+        props,
+        pairs,
+    })
+}
+
+impl Legacy {
+    // value of a UUID, falls back to the UUID to keep entries apart
+    fn value(&self, branch: &str, uuid: &str) -> String {
+        let prop_dir = match self.branches.get(branch).and_then(|b| b.dir.clone()) {
+            None => branch.to_owned(),
+            Some(d) => d,
+        };
+
+        match self.props.get(&prop_dir).and_then(|vs| vs.get(uuid)) {
+            None => uuid.to_owned(),
+            Some(v) => v.to_owned(),
+        }
+    }
+
+    fn trunk(&self, branch: &str) -> Option<String> {
+        self.branches.get(branch).and_then(|b| b.trunk.clone())
+    }
+
+    fn leaves(&self, trunk: &str) -> Vec<String> {
+        let mut leaves: Vec<String> = self
+            .branches
+            .keys()
+            .filter(|branch| self.trunk(branch).as_deref() == Some(trunk))
+            .cloned()
+            .collect();
+
+        leaves.sort();
+
+        leaves
+    }
+
+    // grains of a legacy entry as trunk, trunk value, branch, branch value
+    fn grains(&self, branch: &str, uuid: &str) -> BTreeSet<(String, String, String, String)> {
+        self.leaves(branch).iter().fold(BTreeSet::new(), |mut with_leaf, leaf| {
+            let pairs = match self.pairs.get(leaf) {
+                None => vec![],
+                Some(ls) => ls.to_vec(),
+            };
+
+            for pair in pairs.iter().filter(|pair| pair.key == uuid) {
+                with_leaf.insert((
+                    branch.to_owned(),
+                    self.value(branch, uuid),
+                    leaf.to_owned(),
+                    self.value(leaf, &pair.value),
+                ));
+
+                with_leaf.extend(self.grains(leaf, &pair.value));
+            }
+
+            with_leaf
+        })
+    }
+}
+
+fn entry_grains(entry: &Entry) -> BTreeSet<(String, String, String, String)> {
+    entry.leaves.iter().fold(BTreeSet::new(), |mut with_leaf, (leaf, items)| {
+        for item in items {
+            with_leaf.insert((
+                entry.base.to_owned(),
+                entry.base_value.clone().unwrap_or_default(),
+                leaf.to_owned(),
+                item.base_value.clone().unwrap_or_default(),
+            ));
+
+            with_leaf.extend(entry_grains(item));
+        }
+
+        with_leaf
+    })
+}
+
+fn write_lines(filepath: &Path, lines: Vec<Line>) -> Result<()> {
+    let mut lines = lines;
+
+    sort_lines(&mut lines);
+
+    lines.dedup_by(|a, b| a.key == b.key && a.value == b.value);
+
+    if lines.is_empty() {
+        return Ok(());
+    }
+
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_path(filepath)?;
+
+    for line in lines {
+        wtr.serialize(line)?;
+    }
+
+    wtr.flush()?;
+
+    Ok(())
+}
+
+async fn check_migration(dataset: &Dataset, legacy: &Legacy) -> Result<()> {
+    let roots: Vec<String> = legacy
+        .branches
+        .keys()
+        .filter(|branch| legacy.trunk(branch).is_none())
+        .cloned()
+        .collect();
+
+    for root in roots {
+        let mut uuids: Vec<String> = legacy
+            .leaves(&root)
+            .iter()
+            .flat_map(|leaf| match legacy.pairs.get(leaf) {
+                None => vec![],
+                Some(ls) => ls.iter().map(|l| l.key.to_owned()).collect(),
+            })
+            .collect();
+
+        uuids.sort();
+
+        uuids.dedup();
+
+        for uuid in uuids.iter().take(MIGRATE_SAMPLE) {
+            let expected = legacy.grains(&root, uuid);
+
+            let value = legacy.value(&root, uuid);
+
+            // query values are regexes, match the literal value
+            let query = Entry {
+                base: root.to_owned(),
+                base_value: Some(format!("^{}$", regex::escape(&value))),
+                base_matcher: None,
+                leader_value: None,
+                leaves: HashMap::new(),
+            };
+
+            let entries = dataset.clone().select_record(vec![query]).await?;
+
+            let received = entries.iter().fold(BTreeSet::new(), |mut with_entry, entry| {
+                with_entry.extend(entry_grains(entry));
+
+                with_entry
+            });
+
+            if !expected.is_subset(&received) {
+                return Err(Error::from_message(format!(
+                    "migrated {} {} does not match legacy {}",
+                    root, value, uuid
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// tablets written before the sorted marker might not be sorted,
+// sort each of them and mark the dataset in one commit
+fn upgrade_dataset(dir: &Path) -> Result<()> {
+    let mut transaction = Transaction::begin(dir)?;
+
+    for filename in list_tablets(dir)? {
+        let mut lines = read_lines(&dir.join(&filename))?;
+
+        sort_lines(&mut lines);
+
+        let mut wtr = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_path(transaction.stage(&filename))?;
+
+        for line in lines {
+            wtr.serialize(line)?;
+        }
+
+        wtr.flush()?;
+    }
+
+    if read_version(dir).is_ok_and(|version| version < FORMAT_VERSION) {
+        write_version(&transaction.stage(".csvs.csv"))?;
+    }
+
+    write_marker(&transaction.stage(SORTED))?;
+
+    transaction.commit()
+}
+
+pub async fn migrate_dataset(dir: &PathBuf) -> Result<Dataset> {
+    let is_legacy = fs::metadata(dir.join("metadir.json")).is_ok();
+
+    if !is_legacy {
+        // open checks the version, so read it before opening
+        transaction::recover(dir)?;
+
+        // leave a newer version for open to refuse
+        let is_unsorted = match read_version(dir) {
+            Err(_) => false,
+            Ok(version) if version < FORMAT_VERSION => true,
+            Ok(version) => check_version(version).is_ok() && read_tablet_order(dir) == Order::Unsorted,
+        };
+
+        if is_unsorted {
+            upgrade_dataset(dir)?;
+        }
+
+        // nothing else to migrate if dataset is current
+        return Dataset::open(dir).await;
+    }
+
+    let legacy = read_legacy(dir)?;
+
+    let mut written = vec![];
+
+    // write schema
+    let schema_lines: Vec<Line> = legacy
+        .branches
+        .keys()
+        .filter_map(|branch| {
+            legacy.trunk(branch).map(|trunk| Line {
+                key: trunk,
+                value: branch.to_owned(),
+            })
+        })
+        .collect();
+
+    written.push(dir.join("_-_.csv"));
+
+    write_lines(&dir.join("_-_.csv"), schema_lines)?;
+
+    // write tablets
+    for (branch, pairs) in legacy.pairs.iter() {
+        let trunk = match legacy.trunk(branch) {
+            None => continue,
+            Some(t) => t,
+        };
+
+        let lines: Vec<Line> = pairs
+            .iter()
+            .map(|pair| Line {
+                key: legacy.value(&trunk, &pair.key),
+                value: legacy.value(branch, &pair.value),
+            })
+            .collect();
+
+        let filepath = dir.join(format!("{}-{}.csv", trunk, branch));
+
+        written.push(filepath.clone());
+
+        write_lines(&filepath, lines)?;
+    }
+
+    write_version(&dir.join(".csvs.csv"))?;
+
+    let dataset = Dataset::new(dir);
+
+    written.push(dir.join(".csvs.csv"));
+
+    if let Err(e) = check_migration(&dataset, &legacy).await {
+        // roll back to the legacy layout
+        for filepath in written {
+            if fs::metadata(&filepath).is_ok() {
+                fs::remove_file(filepath)?;
+            }
+        }
+
+        return Err(e);
+    }
+
+    fs::remove_file(dir.join("metadir.json"))?;
+
+    if fs::metadata(dir.join("metadir")).is_ok() {
+        fs::remove_dir_all(dir.join("metadir"))?;
+    }
+
+    upgrade_dataset(dir)?;
+
+    Dataset::open(dir).await
+}
@@ -1,42 +1,192 @@
+mod check;
 mod create;
 mod delete;
+mod dry;
+mod index;
 mod insert;
+mod migrate;
 mod select;
+mod tablets;
+mod transaction;
+mod types;
 mod update;
-use crate::{Entry, Result, Schema};
+use crate::version::{check_version, read_version};
+use transaction::Transaction;
+use crate::{Entry, Error, Result, Schema, ValueType, Version};
 use futures_core::stream::Stream;
 use serde::{Deserialize, Serialize};
+use std::fs;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+pub use check::TabletProblem;
+pub use delete::Cascade;
+pub use dry::{Changeset, TabletChange};
+pub use select::{Aggregation, Bucket, Cursor, SelectOptions, Sort, SortOrder, ValueOption};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Dataset {
     dir: PathBuf,
+    // read from .csvs.csv on open, none for a dataset that was not opened
+    version: Option<Version>,
+    // tablets edited by a dry run, which its transactions commit to instead of the dataset
+    #[serde(skip)]
+    dry: Option<PathBuf>,
+    // parsed _-_.csv and _-type.csv with their modification times, shared by clones
+    #[serde(skip)]
+    schema: Arc<Mutex<Option<(Vec<Option<SystemTime>>, Schema)>>>,
 }
 
 impl Dataset {
+    /// does not read the directory, so the version is unknown
     pub fn new(dir: &PathBuf) -> Self {
-        Dataset { dir: dir.clone() }
+        Dataset {
+            dir: dir.clone(),
+            version: None,
+            dry: None,
+            schema: Default::default(),
+        }
     }
 
-    pub fn create(&self, name: &str) -> Result<()> {
-        create::create_dataset(self, name);
+    /// finish or roll back an interrupted commit, read .csvs.csv and _-_.csv,
+    /// fail if the format version is not supported or if the schema is not valid
+    pub async fn open(dir: &PathBuf) -> Result<Self> {
+        transaction::recover(dir)?;
+
+        let version = check_version(read_version(dir)?)?;
+
+        let dataset = Dataset {
+            dir: dir.clone(),
+            version: Some(version),
+            dry: None,
+            schema: Default::default(),
+        };
+
+        dataset.schema().await?;
+
+        Ok(dataset)
+    }
+
+    /// format version in .csvs.csv, if the dataset was opened
+    pub fn version(&self) -> Option<Version> {
+        self.version
+    }
+
+    /// schema with declared types, read once and again
+    /// only after _-_.csv or _-type.csv changes,
+    /// fails if the schema is not valid
+    pub async fn schema(&self) -> Result<Schema> {
+        // every read starts here, so no read sees half of a commit
+        transaction::recover(&self.dir)?;
+
+        let mut modified = vec![];
+
+        for filename in ["_-_.csv", types::TYPES] {
+            modified.push(match fs::metadata(self.dir.join(filename)) {
+                Err(_) => None,
+                Ok(m) => Some(m.modified()?),
+            });
+        }
+
+        {
+            let cache = self.schema.lock().map_err(Error::from_message)?;
+
+            if let Some((cached, schema)) = cache.as_ref() {
+                if *cached == modified {
+                    return Ok(schema.clone());
+                }
+            }
+        }
+
+        let schema = self.clone().select_schema().await?;
+
+        let problems = schema.validate();
+
+        if !problems.is_empty() {
+            return Err(Error::invalid_schema(problems));
+        }
+
+        let schema = types::apply_types(schema, &types::read_types(&self.dir)?);
+
+        let mut cache = self.schema.lock().map_err(Error::from_message)?;
+
+        *cache = Some((modified, schema.clone()));
+
+        Ok(schema)
+    }
+
+    // transaction of a mutation, or of its dry run
+    fn begin(&self) -> Result<Transaction> {
+        match &self.dry {
+            None => Transaction::begin(&self.dir),
+            Some(dry) => Transaction::begin_dry(&self.dir, dry),
+        }
+    }
+
+    // an update of _ may land within the same modification time
+    fn forget_schema(&self) -> Result<()> {
+        let mut cache = self.schema.lock().map_err(Error::from_message)?;
+
+        *cache = None;
 
         Ok(())
     }
 
-    pub async fn delete_record(self, query: Vec<Entry>) -> Result<()> {
-        delete::delete_record(self, query).await?;
+    pub async fn create(&self, name: &str, schema: Option<Entry>) -> Result<Dataset> {
+        create::create_dataset(self, name, schema).await
+    }
+
+    /// rewrite a legacy metadir.json dataset into the tablet layout
+    pub async fn migrate(dir: &PathBuf) -> Result<Dataset> {
+        migrate::migrate_dataset(dir).await
+    }
+
+    /// find duplicate, unsorted, unknown and dangling tablet lines, repair them all in one commit if fix
+    pub fn check(self, fix: bool) -> impl Stream<Item = Result<TabletProblem>> {
+        check::check_dataset(self, fix)
+    }
+
+    pub async fn delete_record(self, query: Vec<Entry>, cascade: Cascade) -> Result<()> {
+        delete::delete_record(self, query, cascade).await?;
 
         Ok(())
     }
 
-    pub fn delete_record_stream<S>(self, input: S) -> impl Stream<Item = Result<Entry>>
+    /// yields each entry found by the queries, a bare base value matches only itself,
+    /// and with Cascade::Exclusive
+    /// each descendant removed with the lines of its leaves,
+    /// the entries commit together when the input ends
+    pub fn delete_record_stream<S>(self, input: S, cascade: Cascade) -> impl Stream<Item = Result<Entry>>
     where
         S: Stream<Item = Result<Entry>>,
     {
-        delete::delete_record_stream(self, input)
+        delete::delete_record_stream(self, input, cascade)
+    }
+
+    /// tablet edits the delete would make, without writing the dataset
+    pub async fn delete_record_dry(self, query: Vec<Entry>, cascade: Cascade) -> Result<Changeset> {
+        dry::dry_run(self, |dataset| dataset.delete_record(query, cascade)).await
+    }
+
+    /// write reverse indexes for the given tablets, or for every tablet if none given,
+    /// later mutations keep them current
+    pub fn build_index(self, tablets: Vec<String>) -> Result<()> {
+        index::build_index(self, tablets)
     }
 
+    /// write text indexes of leaf words for the given tablets, or for every tablet
+    /// if none given, which $text queries read and later mutations keep current
+    pub fn build_text_index(self, tablets: Vec<String>) -> Result<()> {
+        index::build_text_index(self, tablets)
+    }
+
+    /// declare the type of a branch in _-type.csv, or remove the declaration if none,
+    /// fails if a value of the branch is not of the type
+    pub async fn set_type(self, branch: &str, value_type: Option<ValueType>) -> Result<()> {
+        types::set_type(self, branch, value_type).await
+    }
+
+    /// all queries commit together when the input ends
     pub async fn insert_record(self, query: Vec<Entry>) -> Result<()> {
         insert::insert_record(self, query).await?;
 
@@ -50,15 +200,46 @@ impl Dataset {
         insert::insert_record_stream(self, input)
     }
 
+    /// tablet edits the insert would make, without writing the dataset
+    pub async fn insert_record_dry(self, query: Vec<Entry>) -> Result<Changeset> {
+        dry::dry_run(self, |dataset| dataset.insert_record(query)).await
+    }
+
     pub async fn select_record(self, query: Vec<Entry>) -> Result<Vec<Entry>> {
         select::select_record(self, query).await
     }
 
-    pub fn select_record_stream<S>(self, input: S) -> impl Stream<Item = Result<Entry>>
+    /// distinct values of a branch that start with the prefix, sorted,
+    /// each with the number of trunk values that have it,
+    /// or of leaf values it has if the branch has no trunks
+    pub fn select_options(self, branch: &str, prefix: Option<&str>) -> impl Stream<Item = Result<ValueOption>> {
+        select::select_options(self, branch.to_owned(), prefix.map(|p| p.to_owned()))
+    }
+
+    /// number of entries that match queries, without reading their values
+    pub async fn count(self, query: Vec<Entry>) -> Result<usize> {
+        select::count_record(self, query).await
+    }
+
+    /// number of entries that match queries for each value of a branch
+    pub async fn aggregate(self, query: Vec<Entry>, aggregation: Aggregation) -> Result<Vec<(String, usize)>> {
+        select::aggregate_record(self, query, aggregation).await
+    }
+
+    /// entries of each query, sorted and paged by options
+    pub fn select_record_stream<S>(self, input: S, options: SelectOptions) -> impl Stream<Item = Result<Entry>>
     where
         S: Stream<Item = Result<Entry>>,
     {
-        select::select_record_stream(self, input)
+        select::select_record_stream(self, input, options)
+    }
+
+    /// entries of each query with cursors to resume the select after them
+    pub fn select_cursor_stream<S>(self, input: S, options: SelectOptions) -> impl Stream<Item = Result<(Entry, Cursor)>>
+    where
+        S: Stream<Item = Result<Entry>>,
+    {
+        select::select_cursor_stream(self, input, options)
     }
 
     pub async fn select_schema(self) -> Result<Schema> {
@@ -83,7 +264,12 @@ impl Dataset {
         update::update_record_stream(self, input)
     }
 
-    pub async fn print_record(self, query: Vec<Entry>) -> Result<()> {
-        select::print_record(self, query).await
+    /// tablet edits the update would make, without writing the dataset
+    pub async fn update_record_dry(self, query: Vec<Entry>) -> Result<Changeset> {
+        dry::dry_run(self, |dataset| dataset.update_record(query)).await
+    }
+
+    pub async fn print_record(self, query: Vec<Entry>, options: SelectOptions) -> Result<()> {
+        select::print_record(self, query, options).await
     }
 }
@@ -0,0 +1,124 @@
+use super::select_query_stream;
+use super::paging::BATCH_SIZE;
+use super::types::filter::branch_values;
+use crate::{Dataset, Entry, Error, Result};
+use futures_util::pin_mut;
+use futures_util::stream::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+
+/// how values of a branch are grouped, dates by their leading part
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum Bucket {
+    #[default]
+    Value,
+    Year,
+    Month,
+    Day,
+}
+
+impl Bucket {
+    fn key(&self, value: &str) -> String {
+        let length = match self {
+            Bucket::Value => return value.to_owned(),
+            Bucket::Year => 4,
+            Bucket::Month => 7,
+            Bucket::Day => 10,
+        };
+
+        // shorter values and values that are not dates stay whole
+        value.get(..length).unwrap_or(value).to_owned()
+    }
+}
+
+/// count of entries for each value of a branch
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Aggregation {
+    pub branch: String,
+    #[serde(default)]
+    pub bucket: Bucket,
+}
+
+/// parses branch, branch:year, branch:month or branch:day
+impl FromStr for Aggregation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (branch, bucket) = match s.rsplit_once(':') {
+            None => (s, Bucket::Value),
+            Some((b, "year")) => (b, Bucket::Year),
+            Some((b, "month")) => (b, Bucket::Month),
+            Some((b, "day")) => (b, Bucket::Day),
+            Some((_, b)) => return Err(Error::from_message(format!("unknown bucket {}", b))),
+        };
+
+        Ok(Aggregation {
+            branch: branch.to_owned(),
+            bucket,
+        })
+    }
+}
+
+/// number of entries that match queries, reads no value tablets
+/// unless the query filters on them
+pub async fn count_record(dataset: Dataset, query: Vec<Entry>) -> Result<usize> {
+    let mut count = 0;
+
+    for q in query {
+        let s = select_query_stream(dataset.clone(), q, BATCH_SIZE, Some(vec![]));
+
+        pin_mut!(s); // needed for iteration
+
+        while let Some(entry) = s.next().await {
+            entry?;
+
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+/// number of entries for each bucket of the branch, sorted by bucket,
+/// reads only value tablets on the way to the branch,
+/// an entry counts once in each of its buckets and not at all without a value
+pub async fn aggregate_record(
+    dataset: Dataset,
+    query: Vec<Entry>,
+    aggregation: Aggregation,
+) -> Result<Vec<(String, usize)>> {
+    let schema = dataset.schema().await?;
+
+    if !schema.0.contains_key(&aggregation.branch) {
+        return Err(Error::unknown_branch(&aggregation.branch));
+    }
+
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+
+    for q in query {
+        let s = select_query_stream(
+            dataset.clone(),
+            q,
+            BATCH_SIZE,
+            Some(vec![aggregation.branch.to_owned()]),
+        );
+
+        pin_mut!(s); // needed for iteration
+
+        while let Some(entry) = s.next().await {
+            let entry = entry?;
+
+            let keys: HashSet<String> = branch_values(&entry, &aggregation.branch)
+                .iter()
+                .map(|value| aggregation.bucket.key(value))
+                .collect();
+
+            for key in keys {
+                *counts.entry(key).or_insert(0) += 1;
+            }
+        }
+    }
+
+    Ok(counts.into_iter().collect())
+}
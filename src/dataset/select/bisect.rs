@@ -0,0 +1,203 @@
+use crate::dataset::tablets::Order;
+use crate::{line::Line, Error, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+// states of a csv field while scanning a tablet byte by byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Start,
+    Unquoted,
+    Quoted,
+    QuoteEnd,
+    Invalid,
+}
+
+// the field after the byte, and if the byte ends a record
+fn step(field: Field, byte: u8) -> (Field, bool) {
+    match (field, byte) {
+        (Field::Invalid, _) => (Field::Invalid, false),
+        (Field::Quoted, b'"') => (Field::QuoteEnd, false),
+        (Field::Quoted, _) => (Field::Quoted, false),
+        (Field::QuoteEnd, b'"') => (Field::Quoted, false),
+        (Field::Start, b'"') => (Field::Quoted, false),
+        (_, b',') => (Field::Start, false),
+        (_, b'\n') => (Field::Start, true),
+        (Field::QuoteEnd, _) | (Field::Unquoted, b'"') => (Field::Invalid, false),
+        _ => (Field::Unquoted, false),
+    }
+}
+
+// first record that starts at or after a line start,
+// a line starts a record unless it is inside a quoted field
+// so read on both ways until one of them is not valid csv
+fn record_at(reader: &mut BufReader<File>, start: u64) -> Result<Option<u64>> {
+    let mut outside = Field::Start;
+
+    let mut inside = Field::Quoted;
+
+    let mut inside_start: Option<u64> = None;
+
+    let mut offset = start;
+
+    for byte in reader.by_ref().bytes() {
+        let byte = byte?;
+
+        offset += 1;
+
+        outside = step(outside, byte).0;
+
+        let (inside_next, is_end) = step(inside, byte);
+
+        inside = inside_next;
+
+        if is_end && inside_start.is_none() {
+            inside_start = Some(offset);
+        }
+
+        if inside == Field::Invalid {
+            return Ok(Some(start));
+        }
+
+        if outside == Field::Invalid && inside_start.is_some() {
+            return Ok(inside_start);
+        }
+    }
+
+    // a quoted field left open at the end is not valid
+    match (outside, inside) {
+        (Field::Quoted | Field::Invalid, _) => Ok(inside_start),
+        _ => Ok(Some(start)),
+    }
+}
+
+// start and key of the first record that starts at or after offset
+fn line_at(reader: &mut BufReader<File>, offset: u64, order: Order) -> Result<Option<(u64, String)>> {
+    let mut raw = vec![];
+
+    let start = if offset == 0 {
+        0
+    } else {
+        // read from the byte before offset
+        // so that a line starting at offset is not skipped
+        reader.seek(SeekFrom::Start(offset - 1))?;
+
+        let skipped = reader.read_until(b'\n', &mut raw)?;
+
+        offset - 1 + skipped as u64
+    };
+
+    let start = match order {
+        Order::Records if start > 0 => match record_at(reader, start)? {
+            None => return Ok(None),
+            Some(s) => s,
+        },
+        _ => start,
+    };
+
+    reader.seek(SeekFrom::Start(start))?;
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(reader);
+
+    match rdr.records().next() {
+        None => Ok(None),
+        Some(record) => {
+            let record = record?;
+
+            Ok(Some((start, record.get(0).unwrap_or("").to_owned())))
+        }
+    }
+}
+
+/// offset of the first record with key not less than the given key,
+/// the tablet must be sorted by key
+pub fn find_key(filepath: &Path, key: &str, order: Order) -> Result<u64> {
+    let len = std::fs::metadata(filepath)?.len();
+
+    let mut reader = BufReader::new(File::open(filepath)?);
+
+    let mut lo = 0;
+
+    let mut hi = len;
+
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+
+        match line_at(&mut reader, mid, order)? {
+            Some((_, key_mid)) if key_mid.as_str() < key => lo = mid + 1,
+            _ => hi = mid,
+        }
+    }
+
+    match line_at(&mut reader, lo, order)? {
+        None => Ok(len),
+        Some((start, _)) => Ok(start),
+    }
+}
+
+/// lines of one key in a sorted tablet
+pub fn read_key(filepath: &Path, key: &str, order: Order) -> Result<Vec<Line>> {
+    let start = find_key(filepath, key, order)?;
+
+    let mut file = File::open(filepath)?;
+
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(file);
+
+    let mut lines = vec![];
+
+    for result in rdr.records() {
+        let record = result.map_err(|e| Error::from_csv(filepath, e))?;
+
+        if record.get(0) != Some(key) {
+            break;
+        }
+
+        lines.push(Line {
+            key: key.to_owned(),
+            value: match record.get(1) { None => String::from(""), Some(s) => s.to_owned() },
+        });
+    }
+
+    Ok(lines)
+}
+
+/// lines of keys that start with the prefix in a sorted tablet
+pub fn read_prefix(filepath: &Path, prefix: &str, order: Order) -> Result<Vec<Line>> {
+    let start = find_key(filepath, prefix, order)?;
+
+    let mut file = File::open(filepath)?;
+
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(file);
+
+    let mut lines = vec![];
+
+    for result in rdr.records() {
+        let record = result.map_err(|e| Error::from_csv(filepath, e))?;
+
+        let key = match record.get(0) {
+            Some(k) if k.starts_with(prefix) => k,
+            _ => break,
+        };
+
+        lines.push(Line {
+            key: key.to_owned(),
+            value: match record.get(1) { None => String::from(""), Some(s) => s.to_owned() },
+        });
+    }
+
+    Ok(lines)
+}
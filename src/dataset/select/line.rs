@@ -1,16 +1,17 @@
 use super::types::state::State;
 use super::types::tablet::Tablet;
-use crate::{line::Line, Entry, Error, Grain, Result};
+use crate::matcher::Pattern;
+use crate::{line::Line, Entry, Error, Grain, Matcher, Result};
 use async_stream::{stream, try_stream};
 use futures_core::stream::Stream;
 use futures_util::stream::StreamExt;
-use regex::Regex;
 use std::collections::HashMap;
 
 fn make_state_initial(state: &State, tablet: &Tablet) -> State {
     let empty_entry = Entry {
         base: tablet.base.to_owned(),
         base_value: None,
+        base_matcher: None,
         leader_value: None,
         leaves: HashMap::new(),
     };
@@ -34,8 +35,10 @@ fn make_state_initial(state: &State, tablet: &Tablet) -> State {
                     let grain = Grain {
                         base: tablet.base.to_owned(),
                         base_value: None,
+                        base_matcher: None,
                         leaf: e.base.to_owned(),
                         leaf_value: e.base_value.clone(),
+                        leaf_matcher: e.base_matcher.clone(),
                     };
 
                     empty_entry.sow(&grain, &tablet.base, &e.base)
@@ -82,11 +85,106 @@ fn make_state_initial(state: &State, tablet: &Tablet) -> State {
     }
 }
 
+/// trait of every line the tablet can match, if the query asks for one literal value
+pub fn lookup_trait(state: &State, tablet: &Tablet) -> Option<String> {
+    // accumulating by trunk remembers things from the second column
+    if tablet.accumulating && !tablet.thing_is_first {
+        return None;
+    }
+
+    let state_initial = make_state_initial(state, tablet);
+
+    let grains = state_initial.query?.mow(&tablet.trait_, &tablet.thing);
+
+    let mut values = grains.iter().map(|grain| {
+        if tablet.trait_is_regex {
+            grain_matcher(grain, tablet).literal()
+        } else {
+            grain_value(grain, tablet).clone()
+        }
+    });
+
+    let value = values.next()??;
+
+    if !values.all(|v| v.as_ref() == Some(&value)) {
+        return None;
+    }
+
+    Some(value)
+}
+
+/// traits that lines of a value tablet must have for this state
+pub fn value_traits(state: &State, tablet: &Tablet) -> Vec<String> {
+    let state_initial = make_state_initial(state, tablet);
+
+    match state_initial.query {
+        None => vec![],
+        Some(q) => q
+            .mow(&tablet.trait_, &tablet.thing)
+            .into_iter()
+            .filter_map(|grain| grain.base_value)
+            .collect(),
+    }
+}
+
+// value of the grain that a line of the tablet must have
+fn grain_value<'a>(grain: &'a Grain, tablet: &Tablet) -> &'a Option<String> {
+    if tablet.trait_is_first {
+        &grain.base_value
+    } else {
+        &grain.leaf_value
+    }
+}
+
+// operators that a line of a regex tablet must match for the grain,
+// a grain without a value matches any line
+fn grain_matcher(grain: &Grain, tablet: &Tablet) -> Matcher {
+    let matcher = if tablet.trait_is_first {
+        &grain.base_matcher
+    } else {
+        &grain.leaf_matcher
+    };
+
+    match (matcher, grain_value(grain, tablet)) {
+        (Some(m), _) => m.clone(),
+        (None, Some(s)) => Matcher::regex(s),
+        (None, None) => Matcher::regex(""),
+    }
+}
+
+// pattern of each grain in a regex tablet, compiled with the query
+// unless tablets before this one sowed the value into the query
+fn grain_patterns(grains: &[Grain], tablet: &Tablet) -> Result<Vec<Option<Pattern>>> {
+    let mut patterns = vec![];
+
+    for grain in grains {
+        if !tablet.trait_is_regex {
+            patterns.push(None);
+
+            continue;
+        }
+
+        let matcher = grain_matcher(grain, tablet);
+
+        let pattern = match tablet.patterns.get(&matcher) {
+            Some(pattern) => pattern.clone(),
+            None => matcher
+                .compile_as(tablet.trait_type)
+                .map_err(|e| Error::invalid_query(format!("$.{}", tablet.trait_), e))?,
+        };
+
+        patterns.push(Some(pattern));
+    }
+
+    Ok(patterns)
+}
+
 fn make_state_line(
     state_initial: &State,
     state: &mut State,
     tablet: &Tablet,
-    grains: &Vec<Grain>,
+    grains: &[Grain],
+    patterns: &[Option<Pattern>],
     trait_: String,
     thing: String,
 ) -> Result<()> {
@@ -97,42 +195,22 @@ fn make_state_line(
     let grain_new = Grain {
         base: tablet.trait_.to_owned(),
         base_value: Some(trait_.to_owned()),
+        base_matcher: None,
         leaf: tablet.thing.to_owned(),
         leaf_value: Some(thing.to_owned()),
+        leaf_matcher: None,
     };
 
     // if tablet.filename == "datum-filepath.csv" {println!("{} {}", tablet.filename, serde_json::to_string_pretty(&grain_new)?)};
-    for grain in grains {
+    for (grain, pattern) in grains.iter().zip(patterns) {
         // println!("{} {}", tablet.filename, serde_json::to_string_pretty(&grain)?);
 
         // println!("{} {} {}", tablet.filename, tablet.trait_, trait_);
-        let re_str: String = if tablet.trait_is_first {
-            match &grain.base_value {
-                None => String::from(""),
-                Some(s) => s.to_owned(),
-            }
-        } else {
-            match &grain.leaf_value {
-                None => String::from(""),
-                Some(s) => s.to_owned(),
-            }
-        };
-
-        // if tablet.filename == "datum-filepath.csv" {println!("{} {} {:?}", tablet.filename, tablet.trait_is_first, foo.clone())};
-
-        let is_match_grain = if tablet.trait_is_regex {
-            let re = Regex::new(&re_str)?;
-
-            // if tablet.filename == "datum-filepath.csv" {println!("{} {} {} {}", tablet.filename, foo.clone(), trait_, re.is_match(&trait_))};
-
-            re.is_match(&trait_)
-        } else {
-            // if tablet.filename == "datum-filepath.csv" {println!("{}, {}, {}, {}", tablet.filename, foo.clone(), trait_, foo.clone() == trait_)};
-            re_str == trait_.clone()
+        let is_match_grain = match pattern {
+            Some(re) => re.is_match(&trait_),
+            None => grain_value(grain, tablet).as_deref().unwrap_or("") == trait_,
         };
 
-        // println!("{} {}", tablet.filename, is_match_grain);
-
         // when querying also match literal trait from the query
         // otherwise always true
         let do_diff = tablet.querying && state_initial.thing_querying.is_some();
@@ -233,6 +311,8 @@ pub fn select_line_stream<S: Stream<Item = Result<Line>>>(
     // if tablet.filename == "datum-filepath.csv" {println!("{} {}", tablet.filename, serde_json::to_string_pretty(&grains)?)};
 
     try_stream! {
+        let patterns = grain_patterns(&grains, &tablet)?;
+
         for await line in input {
             let line = line?;
 
@@ -293,7 +373,7 @@ pub fn select_line_stream<S: Stream<Item = Result<Line>>>(
             // println!("{} {} {} {}", tablet.filename, tablet.passthrough, trait_, thing);
 
             // if tablet.accumulating {println!("{:?} \n {:#?}", tablet, line)};
-            make_state_line(&state_initial, &mut state_current, &tablet, &grains, trait_, thing)?;
+            make_state_line(&state_initial, &mut state_current, &tablet, &grains, &patterns, trait_, thing)?;
 
             // if tablet.filename == "datum-filepath.csv" {
                 // println!("{} {}", tablet.filename, serde_json::to_string_pretty(&state_current)?)
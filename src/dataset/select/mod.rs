@@ -1,19 +1,35 @@
-use crate::{Error, Result, Dataset, Entry, Schema};
+use crate::{Error, Result, Dataset, Entry, Matcher, Operator, Schema, ValueType};
+mod aggregate;
+mod bisect;
 mod line;
+mod options;
+mod paging;
 mod schema;
 mod strategy;
 mod types;
-use strategy::{plan_select, plan_select_schema};
+mod values;
+use strategy::{merge_matcher, plan_groups, plan_select, plan_select_schema, Strategy};
+use text::{search_text, take_text};
+use super::tablets::{read_tablet_order, Order};
+use super::index::list_indexes;
 mod tablet;
+mod text;
 use async_stream::{stream, try_stream};
 use futures_core::stream::{BoxStream, Stream};
 use futures_util::pin_mut;
-use futures_util::stream::StreamExt;
-use std::collections::HashMap;
+use futures_util::future;
+use futures_util::stream::{self, StreamExt};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
 use tablet::select_tablet;
+use paging::{fuzzy_terms, page_stream};
+pub use aggregate::{aggregate_record, count_record, Aggregation, Bucket};
+pub use options::{select_options, ValueOption};
+pub use paging::{Cursor, SelectOptions, Sort, SortOrder};
+use types::filter::Filter;
 use types::state::State;
+use values::select_values_stream;
 use serde_json::Value;
 
 pub fn select_schema_stream<S: Stream<Item = Result<Entry>>>(
@@ -45,7 +61,7 @@ pub fn select_schema_stream<S: Stream<Item = Result<Entry>>>(
                 let mut stream: BoxStream<'static, Result<State>> = Box::pin(query_stream);
 
                 for tablet in strategy {
-                    stream = Box::pin(select_tablet(dataset.dir.clone(), tablet, stream));
+                    stream = Box::pin(select_tablet(dataset.dir.clone(), Order::Unsorted, tablet, stream));
                 }
 
                 for await state in stream {
@@ -66,6 +82,7 @@ pub async fn select_schema(dataset: Dataset) -> Result<Schema> {
         yield Entry {
             base: "_".to_owned(),
             base_value: Some("_".to_owned()),
+            base_matcher: None,
             leader_value: None,
             leaves: HashMap::new(),
         };
@@ -86,119 +103,221 @@ pub async fn select_schema(dataset: Dataset) -> Result<Schema> {
     Ok(entries[0].clone().try_into()?)
 }
 
-pub fn select_record_stream<S: Stream<Item = Result<Entry>>>(
+// find entries of a query without $or, and keep those that pass its filters
+fn select_group_stream(
+    path: PathBuf,
+    strategy: Strategy,
+    query: Entry,
+    filters: Vec<Filter>,
+    batch_size: usize,
+) -> impl Stream<Item = Result<State>> {
+    let query_stream = try_stream! {
+        yield State {
+            entry: None,
+            query: Some(query),
+            is_match: false,
+            has_match: false,
+            thing_querying: None,
+            fst: None,
+            match_map: Some(HashMap::new()),
+        };
+    };
+
+    let mut stream: BoxStream<'static, Result<State>> = Box::pin(query_stream);
+
+    let order = read_tablet_order(&path);
+
+    for tablet in strategy.base {
+        stream = Box::pin(select_tablet(path.clone(), order, tablet, stream));
+    }
+
+    let stream = select_values_stream(path, order, strategy.levels, batch_size, stream);
+
+    stream.filter(move |state| {
+        let is_match = match state {
+            Ok(State { entry: Some(e), .. }) => filters.iter().all(|filter| filter.is_match(e)),
+            _ => true,
+        };
+
+        future::ready(is_match)
+    })
+}
+
+// entries of one query, with values of only the given branches if any
+fn select_query_stream(
     dataset: Dataset,
-    input: S,
+    query: Entry,
+    batch_size: usize,
+    branches: Option<Vec<String>>,
 ) -> impl Stream<Item = Result<Entry>> {
     try_stream! {
-        for await query in input {
-            let query = query?;
+        let is_schema = query.base == "_";
+
+        if is_schema {
+            // TODO merge with select_schema_stream
+            let strategy = plan_select_schema(&query);
+
+            let query_stream = try_stream! {
+                yield State {
+                    entry: None,
+                    query: Some(query),
+                    match_map: Some(HashMap::new()),
+                    has_match: false,
+                    is_match: false,
+                    fst: None,
+                    thing_querying: None
+                };
+            };
 
-            let is_schema = query.base == "_";
+            let mut stream: BoxStream<'static, Result<State>> = Box::pin(query_stream);
 
-            if is_schema {
-                // TODO merge with select_schema_stream
-                let strategy = plan_select_schema(&query);
+            for tablet in strategy {
+                stream = Box::pin(select_tablet(dataset.dir.clone(), Order::Unsorted, tablet, stream));
+            }
 
-                let query_stream = try_stream! {
-                    yield State {
-                        entry: None,
-                        query: Some(query),
-                        match_map: Some(HashMap::new()),
-                        has_match: false,
-                        is_match: false,
-                        fst: None,
-                        thing_querying: None
-                    };
-                };
+            for await state in stream {
+                let state = state?;
 
-                let mut stream: BoxStream<'static, Result<State>> = Box::pin(query_stream);
-
-                for tablet in strategy {
-                    stream = Box::pin(select_tablet(dataset.dir.clone(), tablet, stream));
+                match state.entry {
+                    None => (),
+                    Some(e) => yield e
                 }
+            }
+        } else {
+            let schema = dataset.schema().await?;
 
-                for await state in stream {
-                    let state = state?;
+            if !schema.0.contains_key(&query.base) {
+                Err(Error::unknown_branch(&query.base))?;
+            }
 
-                    match state.entry {
-                        None => (),
-                        Some(e) => yield e
-                    }
-                }
-            } else {
-                let schema = dataset.clone().select_schema().await?;
+            let indexes = list_indexes(&dataset.dir)?;
 
-                let strategy = plan_select(&schema, &query);
+            // a query with $or runs once for each of its groups
+            let groups = plan_groups(&schema, &query)?;
 
-                let query_push = query.clone();
+            let is_union = groups.len() > 1;
 
-                let query_stream = try_stream! {
-                    yield State {
-                        entry: None,
-                        query: Some(query_push),
-                        is_match: false,
-                        has_match: false,
-                        thing_querying: None,
-                        fst: None,
-                        match_map: Some(HashMap::new()),
-                    };
-                };
+            // words of $text become the base values that have them
+            let mut groups_searched = vec![];
 
+            for (mut query_group, filters) in groups {
+                let tokens = take_text(&mut query_group)?;
 
-                let mut stream: BoxStream<'static, Result<State>> = Box::pin(query_stream);
+                if !tokens.is_empty() {
+                    let dir = dataset.dir.clone();
 
-                for tablet in strategy {
-                    stream = Box::pin(select_tablet(dataset.dir.clone(), tablet, stream));
+                    let schema = schema.clone();
+
+                    let base = query_group.base.to_owned();
+
+                    let base_values = tokio::task::spawn_blocking(move || {
+                        search_text(&dir, &schema, &base, &tokens)
+                    })
+                    .await
+                    .map_err(Error::from_message)??;
+
+                    let matcher = Matcher(vec![Operator::In(base_values)]);
+
+                    merge_matcher(&mut query_group, Some(matcher));
                 }
 
-                for await state in stream {
-                    let state = state?;
+                groups_searched.push((query_group, filters));
+            }
+
+            // compile the patterns of every group before reading any tablet
+            let mut groups_planned = vec![];
+
+            for (query_group, filters) in groups_searched {
+                let strategy = plan_select(&schema, &query_group, &indexes)?;
+
+                groups_planned.push((query_group, filters, strategy));
+            }
+
+            let streams: Vec<BoxStream<'static, Result<State>>> = groups_planned
+                .into_iter()
+                .map(|(query_group, filters, strategy)| {
+                    // the leader can be any branch of the entry
+                    let strategy = match (&branches, &query.leader_value) {
+                        (Some(bs), None) => {
+                            let branches_filtered: Vec<String> =
+                                filters.iter().map(|filter| filter.branch.to_owned()).collect();
 
-                    // TODO move to leader stream
-                    let base_new = match &state.entry {
-                        None => continue,
-                        Some(e) => if e.base == query.base {
-                            &e.base
-                        } else {
-                            &query.base
+                            strategy.prune(&schema, &[bs.clone(), branches_filtered].concat())
                         }
+                        _ => strategy,
                     };
 
-                    // if query has __, return leader
-                    // TODO what if leader is nested? what if many leaders? use mow
-                    match &query.leader_value {
-                        None => {
-                            match &state.entry {
-                                None => (),
-                                Some(e) => {
-                                    let mut entry = e.clone();
-
-                                    entry.base = base_new.to_owned();
-
-                                    // do not return search result
-                                    // if state comes from the end of accumulating
-                                    if state.match_map.is_none() {
-                                        yield entry;
-                                    }
+                    let s = select_group_stream(
+                        dataset.dir.clone(),
+                        strategy,
+                        query_group,
+                        filters,
+                        batch_size,
+                    );
+
+                    Box::pin(s) as BoxStream<'static, Result<State>>
+                })
+                .collect();
+
+            let stream = stream::iter(streams).flatten();
+
+            let mut base_values_found = HashSet::new();
+
+            for await state in stream {
+                let state = state?;
+
+                // TODO move to leader stream
+                let base_new = match &state.entry {
+                    None => continue,
+                    Some(e) => if e.base == query.base {
+                        &e.base
+                    } else {
+                        &query.base
+                    }
+                };
+
+                // groups of $or can find the same entry
+                if is_union {
+                    if let Some(e) = &state.entry {
+                        if !base_values_found.insert(e.base_value.clone()) {
+                            continue;
+                        }
+                    }
+                }
+
+                // if query has __, return leader
+                // TODO what if leader is nested? what if many leaders? use mow
+                match &query.leader_value {
+                    None => {
+                        match &state.entry {
+                            None => (),
+                            Some(e) => {
+                                let mut entry = e.clone();
+
+                                entry.base = base_new.to_owned();
+
+                                // do not return search result
+                                // if state comes from the end of accumulating
+                                if state.match_map.is_none() {
+                                    yield entry;
                                 }
                             }
-                        },
-                        Some(s) => {
-                            match &state.query {
-                                None => (),
-                                Some(q) => {
-                                    match q.leaves.get(s) {
-                                        None => (),
-                                        Some(ls) => {
-                                            match ls.first() {
-                                               None => (),
-                                                Some(l) => {
-                                                    // do not return search result
-                                                    // if state comes from the end of accumulating
-                                                    if state.match_map.is_none() {
-                                                        yield l.clone();
-                                                    }
+                        }
+                    },
+                    Some(s) => {
+                        match &state.query {
+                            None => (),
+                            Some(q) => {
+                                match q.leaves.get(s) {
+                                    None => (),
+                                    Some(ls) => {
+                                        match ls.first() {
+                                           None => (),
+                                            Some(l) => {
+                                                // do not return search result
+                                                // if state comes from the end of accumulating
+                                                if state.match_map.is_none() {
+                                                    yield l.clone();
                                                 }
                                             }
                                         }
@@ -206,14 +325,76 @@ pub fn select_record_stream<S: Stream<Item = Result<Entry>>>(
                                 }
                             }
                         }
-                    };
-                }
-            };
+                    }
+                };
+            }
+        };
+    }
+}
+
+// pages of each query, with the cursor of each entry
+fn select_page_stream<S: Stream<Item = Result<Entry>>>(
+    dataset: Dataset,
+    input: S,
+    options: SelectOptions,
+    is_ordered: bool,
+) -> impl Stream<Item = Result<(Entry, Cursor)>> {
+    try_stream! {
+        // sort values compare in the order of their type
+        let mut sort_type = ValueType::String;
+
+        if let Some(sort) = &options.sort {
+            let schema = dataset.schema().await?;
+
+            if !schema.0.contains_key(&sort.branch) {
+                Err(Error::unknown_branch(&sort.branch))?;
+            }
 
+            sort_type = schema.value_type(&sort.branch);
+        }
+
+        for await query in input {
+            let query = query?;
+
+            let fuzzy = fuzzy_terms(&query);
+
+            let s = page_stream(
+                select_query_stream(dataset.clone(), query, options.batch_size(), None),
+                options.clone(),
+                sort_type,
+                is_ordered,
+                fuzzy,
+            );
+
+            pin_mut!(s); // needed for iteration
+
+            while let Some(page) = s.next().await {
+                let page = page?;
+
+                yield page;
+            }
         }
     }
 }
 
+pub fn select_record_stream<S: Stream<Item = Result<Entry>>>(
+    dataset: Dataset,
+    input: S,
+    options: SelectOptions,
+) -> impl Stream<Item = Result<Entry>> {
+    select_page_stream(dataset, input, options, false).map(|page| page.map(|(entry, _)| entry))
+}
+
+/// entries with cursors to resume after, ordered by base value without a sort
+/// so that entries inserted between pages do not shift them
+pub fn select_cursor_stream<S: Stream<Item = Result<Entry>>>(
+    dataset: Dataset,
+    input: S,
+    options: SelectOptions,
+) -> impl Stream<Item = Result<(Entry, Cursor)>> {
+    select_page_stream(dataset, input, options, true)
+}
+
 pub async fn select_record(dataset: Dataset, query: Vec<Entry>) -> Result<Vec<Entry>> {
     let mut entries = vec![];
 
@@ -223,7 +404,7 @@ pub async fn select_record(dataset: Dataset, query: Vec<Entry>) -> Result<Vec<En
         }
     };
 
-    let s = dataset.select_record_stream(readable_stream);
+    let s = dataset.select_record_stream(readable_stream, SelectOptions::default());
 
     pin_mut!(s); // needed for iteration
 
@@ -236,21 +417,24 @@ pub async fn select_record(dataset: Dataset, query: Vec<Entry>) -> Result<Vec<En
     Ok(entries)
 }
 
-pub async fn print_record(dataset: Dataset, query: Vec<Entry>) -> Result<()> {
+pub async fn print_record(dataset: Dataset, query: Vec<Entry>, options: SelectOptions) -> Result<()> {
     let readable_stream = try_stream! {
         for q in query {
             yield q;
         }
     };
 
-    let s = dataset.select_record_stream(readable_stream);
+    // values of typed branches print as json numbers and booleans
+    let schema = dataset.schema().await?;
+
+    let s = dataset.select_record_stream(readable_stream, options);
 
     pin_mut!(s); // needed for iteration
 
     while let Some(entry) = s.next().await {
         let entry = entry?;
 
-        println!("{}", entry);
+        println!("{}", entry.into_typed_value(&schema));
     }
 
     Ok(())
@@ -0,0 +1,99 @@
+use crate::{Branch, Dataset, Error, Leaves, Result, Trunks};
+use async_stream::try_stream;
+use futures_core::stream::Stream;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+
+/// distinct value of a branch with the number of trunk values that have it,
+/// or the number of leaf values it has in a branch without trunks
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ValueOption {
+    pub value: String,
+    pub count: usize,
+}
+
+// add the key or the value of each line that starts with the prefix,
+// counting the lines if asked
+fn scan_options(
+    filepath: &Path,
+    is_key: bool,
+    is_counted: bool,
+    prefix: &str,
+    counts: &mut BTreeMap<String, usize>,
+) -> Result<()> {
+    if std::fs::metadata(filepath).is_err() {
+        return Ok(());
+    }
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(File::open(filepath)?);
+
+    for result in rdr.records() {
+        let record = result.map_err(|e| Error::from_csv(filepath, e))?;
+
+        let value = match record.get(if is_key { 0 } else { 1 }) {
+            None => continue,
+            Some(s) => s,
+        };
+
+        if !value.starts_with(prefix) {
+            continue;
+        }
+
+        let count = counts.entry(value.to_owned()).or_insert(0);
+
+        if is_counted {
+            *count += 1;
+        }
+    }
+
+    Ok(())
+}
+
+/// every distinct value of the branch that starts with the prefix, sorted,
+/// from keys of its leaf tablets and values of its trunk tablets
+pub fn select_options(
+    dataset: Dataset,
+    branch: String,
+    prefix: Option<String>,
+) -> impl Stream<Item = Result<ValueOption>> {
+    try_stream! {
+        let schema = dataset.schema().await?;
+
+        let (trunks, leaves) = match schema.0.get(&branch) {
+            None => Err(Error::unknown_branch(&branch))?,
+            Some(Branch {
+                trunks: Trunks(ts),
+                leaves: Leaves(ls),
+                ..
+            }) => (ts.to_vec(), ls.to_vec()),
+        };
+
+        let prefix = prefix.unwrap_or_default();
+
+        let mut counts = BTreeMap::new();
+
+        // values of a root have no trunk values, so count their own lines
+        let is_root = trunks.is_empty();
+
+        for leaf in leaves {
+            let filepath = dataset.dir.join(format!("{}-{}.csv", branch, leaf));
+
+            scan_options(&filepath, true, is_root, &prefix, &mut counts)?;
+        }
+
+        for trunk in trunks {
+            let filepath = dataset.dir.join(format!("{}-{}.csv", trunk, branch));
+
+            scan_options(&filepath, false, true, &prefix, &mut counts)?;
+        }
+
+        for (value, count) in counts {
+            yield ValueOption { value, count };
+        }
+    }
+}
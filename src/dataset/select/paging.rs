@@ -0,0 +1,315 @@
+use super::types::filter::branch_values;
+use crate::matcher::{edit_distance, fold};
+use crate::{Entry, Error, Result, ValueType};
+use async_stream::try_stream;
+use futures_core::stream::Stream;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::str::FromStr;
+
+// entries that value tablets find at once
+pub const BATCH_SIZE: usize = 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+/// order of entries by the values of a branch,
+/// entries without a value come last
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Sort {
+    pub branch: String,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+/// parses branch, branch:asc or branch:desc
+impl FromStr for Sort {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (branch, order) = match s.rsplit_once(':') {
+            None => (s, SortOrder::Ascending),
+            Some((b, "asc")) => (b, SortOrder::Ascending),
+            Some((b, "desc")) => (b, SortOrder::Descending),
+            Some((_, o)) => return Err(Error::from_message(format!("unknown sort order {}", o))),
+        };
+
+        Ok(Sort {
+            branch: branch.to_owned(),
+            order,
+        })
+    }
+}
+
+/// position of an entry in sorted results, a later select resumes after it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Cursor {
+    pub branch: Option<String>,
+    pub value: Option<String>,
+    pub base_value: Option<String>,
+    /// edits from the fuzzy terms of the query, when they rank entries
+    #[serde(default)]
+    pub score: Option<usize>,
+}
+
+/// opaque string for clients to hand back
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+
+        for byte in json.as_bytes() {
+            write!(f, "{:02x}", byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let bytes = (0..s.len())
+            .step_by(2)
+            .map(|i| s.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| Error::from_message("invalid cursor"))?;
+
+        serde_json::from_slice(&bytes).map_err(|_| Error::from_message("invalid cursor"))
+    }
+}
+
+/// branches with the folded terms of fuzzy operators in a query
+pub fn fuzzy_terms(query: &Entry) -> Vec<(String, String)> {
+    let mut terms = vec![];
+
+    if let Some((term, _)) = query.base_matcher.as_ref().and_then(|m| m.fuzzy()) {
+        terms.push((query.base.to_owned(), term));
+    }
+
+    for items in query.leaves.values() {
+        for item in items {
+            terms.extend(fuzzy_terms(item));
+        }
+    }
+
+    terms
+}
+
+// edits from each term to the closest value of its branch
+fn score(fuzzy: &[(String, String)], entry: &Entry) -> usize {
+    fuzzy
+        .iter()
+        .map(|(branch, term)| {
+            branch_values(entry, branch)
+                .iter()
+                .map(|value| edit_distance(&fold(value), term))
+                .min()
+                .unwrap_or(term.chars().count())
+        })
+        .sum()
+}
+
+impl Cursor {
+    // an entry with many values of the sort branch is placed by the least
+    // or the greatest of them, in the order of the type of the branch
+    fn new(
+        sort: &Option<Sort>,
+        value_type: ValueType,
+        fuzzy: &[(String, String)],
+        entry: &Entry,
+    ) -> Self {
+        let value = sort.as_ref().and_then(|sort| {
+            let values = branch_values(entry, &sort.branch).into_iter();
+
+            match sort.order {
+                SortOrder::Ascending => values.min_by(|a, b| value_type.compare(a, b)),
+                SortOrder::Descending => values.max_by(|a, b| value_type.compare(a, b)),
+            }
+        });
+
+        // an explicit sort takes the place of similarity
+        let score = match sort {
+            None if !fuzzy.is_empty() => Some(score(fuzzy, entry)),
+            _ => None,
+        };
+
+        Cursor {
+            branch: sort.as_ref().map(|sort| sort.branch.to_owned()),
+            value,
+            base_value: entry.base_value.clone(),
+            score,
+        }
+    }
+}
+
+// by score, by sort value with missing values last, then by base value
+fn compare(order: SortOrder, value_type: ValueType, a: &Cursor, b: &Cursor) -> Ordering {
+    let by_score = a.score.cmp(&b.score);
+
+    let by_value = match (&a.value, &b.value) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => match order {
+            SortOrder::Ascending => value_type.compare(a, b),
+            SortOrder::Descending => value_type.compare(b, a),
+        },
+    };
+
+    by_score
+        .then(by_value)
+        .then_with(|| a.base_value.cmp(&b.base_value))
+}
+
+/// how select orders and pages the entries of each query
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct SelectOptions {
+    pub sort: Option<Sort>,
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+    /// only entries after the cursor, in the order of the sort
+    pub after: Option<Cursor>,
+}
+
+impl SelectOptions {
+    /// without a sort, no more entries than the page are needed
+    pub fn batch_size(&self) -> usize {
+        match (&self.sort, &self.after, self.limit) {
+            (None, None, Some(limit)) => (self.offset + limit).clamp(1, BATCH_SIZE),
+            _ => BATCH_SIZE,
+        }
+    }
+}
+
+// entry with its cursor and the order it was found in
+struct Ranked {
+    cursor: Cursor,
+    order: SortOrder,
+    value_type: ValueType,
+    index: usize,
+    entry: Entry,
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare(self.order, self.value_type, &self.cursor, &other.cursor).then(self.index.cmp(&other.index))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+/// skip the offset and stop after the limit, sorting first if asked
+/// in the order of the value type of the sort branch,
+/// ordered pages are sorted by base value without a sort,
+/// and entries are ranked by edits from fuzzy terms without a sort
+pub fn page_stream<S: Stream<Item = Result<Entry>>>(
+    input: S,
+    options: SelectOptions,
+    value_type: ValueType,
+    is_ordered: bool,
+    fuzzy: Vec<(String, String)>,
+) -> impl Stream<Item = Result<(Entry, Cursor)>> {
+    try_stream! {
+        if options.limit == Some(0) {
+            return;
+        }
+
+        let order = match &options.sort {
+            None => SortOrder::Ascending,
+            Some(sort) => sort.order,
+        };
+
+        if let Some(after) = &options.after {
+            if after.branch != options.sort.as_ref().map(|sort| sort.branch.to_owned()) {
+                Err(Error::from_message("cursor is from a select with another sort"))?;
+            }
+        }
+
+        let is_ranked = options.sort.is_none() && !fuzzy.is_empty();
+
+        if !is_ordered && !is_ranked && options.sort.is_none() && options.after.is_none() {
+            let mut index = 0;
+
+            for await entry in input {
+                let entry = entry?;
+
+                index += 1;
+
+                if index <= options.offset {
+                    continue;
+                }
+
+                let cursor = Cursor::new(&options.sort, value_type, &fuzzy, &entry);
+
+                yield (entry, cursor);
+
+                // stop reading tablets when the page is full
+                if options.limit.map(|limit| options.offset + limit) == Some(index) {
+                    break;
+                }
+            }
+
+            return;
+        }
+
+        // keep only the entries that can still be on the page
+        let capacity = options.limit.map(|limit| options.offset + limit);
+
+        let mut heap = BinaryHeap::new();
+
+        let mut index = 0;
+
+        for await entry in input {
+            let entry = entry?;
+
+            let cursor = Cursor::new(&options.sort, value_type, &fuzzy, &entry);
+
+            let is_after = match &options.after {
+                None => true,
+                Some(after) => compare(order, value_type, &cursor, after) == Ordering::Greater,
+            };
+
+            if !is_after {
+                continue;
+            }
+
+            heap.push(Ranked {
+                cursor,
+                order,
+                value_type,
+                index,
+                entry,
+            });
+
+            index += 1;
+
+            if capacity.is_some_and(|c| heap.len() > c) {
+                heap.pop();
+            }
+        }
+
+        for ranked in heap.into_sorted_vec().into_iter().skip(options.offset) {
+            yield (ranked.entry, ranked.cursor);
+        }
+    }
+}
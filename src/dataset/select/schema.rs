@@ -31,6 +31,7 @@ pub fn select_schema_line_stream<S: Stream<Item = Result<Line>>>(
             let leaves_new = [leaves.clone(), vec![Entry {
                 base: trunk.to_owned(),
                 base_value: Some(leaf.to_owned()),
+                base_matcher: None,
                 leader_value: None,
                 leaves: HashMap::new()
             }]].concat();
@@ -1,5 +1,9 @@
+use super::text::TEXT;
+use super::types::filter::Filter;
 use super::types::tablet::Tablet;
-use crate::{Branch, Entry, Leaves, Schema, Trunks};
+use crate::matcher::Pattern;
+use crate::{Branch, Entry, Error, Leaves, Matcher, Result, Schema, Trunks, ValueType};
+use std::collections::{HashMap, HashSet};
 use futures_util::stream::StreamExt;
 use serde::{Deserialize, Serialize};
 
@@ -16,6 +20,9 @@ pub fn plan_select_schema(query: &Entry) -> Vec<Tablet> {
         querying: false,
         eager: false,
         accumulating: false,
+        reverse_index: false,
+        trait_type: ValueType::String,
+        patterns: HashMap::new(),
     }]
 }
 
@@ -45,7 +52,7 @@ fn gather_keys(query: &Entry) -> Vec<String> {
     })
 }
 
-pub fn plan_query(schema: &Schema, query: &Entry) -> Vec<Tablet> {
+pub fn plan_query(schema: &Schema, query: &Entry, indexes: &[String]) -> Vec<Tablet> {
     let mut queried_branches = gather_keys(query);
 
     queried_branches.sort_by(schema.clone().sort_nesting_ascending());
@@ -72,6 +79,10 @@ pub fn plan_query(schema: &Schema, query: &Entry) -> Vec<Tablet> {
                 querying: true,
                 eager: true,
                 accumulating: false,
+                // filter on the leaf value by key of the index
+                reverse_index: indexes.contains(&format!("{}-{}.csv", trunk, branch)),
+                trait_type: schema.value_type(branch),
+                patterns: HashMap::new(),
             })
             .collect();
 
@@ -89,6 +100,7 @@ pub fn plan_options(schema: &Schema, base: &str) -> Vec<Tablet> {
         Some(Branch {
             trunks: Trunks(ts),
             leaves: Leaves(ls),
+            ..
         }) => (ts.to_vec(), ls.to_vec()),
     };
 
@@ -106,6 +118,9 @@ pub fn plan_options(schema: &Schema, base: &str) -> Vec<Tablet> {
             querying: false,
             eager: true,
             accumulating: true,
+            reverse_index: false,
+            trait_type: schema.value_type(trunk),
+            patterns: HashMap::new(),
         })
         .collect();
 
@@ -123,6 +138,9 @@ pub fn plan_options(schema: &Schema, base: &str) -> Vec<Tablet> {
             passthrough: false,
             querying: false,
             eager: true,
+            reverse_index: false,
+            trait_type: schema.value_type(base),
+            patterns: HashMap::new(),
         })
         .collect();
 
@@ -162,6 +180,9 @@ pub fn plan_values(schema: &Schema, query: &Entry) -> Vec<Tablet> {
                 passthrough: true,
                 querying: false,
                 eager: *trunk == query.base,
+                reverse_index: false,
+                trait_type: schema.value_type(trunk),
+                patterns: HashMap::new(),
             })
             .collect();
 
@@ -171,16 +192,287 @@ pub fn plan_values(schema: &Schema, query: &Entry) -> Vec<Tablet> {
     value_tablets
 }
 
-pub fn plan_select(schema: &Schema, query: &Entry) -> Vec<Tablet> {
-    let strategy_query = plan_query(schema, query);
+/// tablets that find entries, then value tablets
+/// in levels that only depend on the levels before them
+#[derive(Debug, Clone)]
+pub struct Strategy {
+    pub base: Vec<Tablet>,
+    pub levels: Vec<Vec<Tablet>>,
+}
+
+impl Strategy {
+    /// only the value tablets that lead to the branches
+    pub fn prune(self, schema: &Schema, branches: &[String]) -> Strategy {
+        let mut needed: HashSet<String> = HashSet::new();
+
+        let mut pending: Vec<String> = branches.to_vec();
+
+        while let Some(branch) = pending.pop() {
+            if !needed.insert(branch.to_owned()) {
+                continue;
+            }
+
+            if let Some(Branch { trunks: Trunks(ts), .. }) = schema.0.get(&branch) {
+                pending.extend(ts.iter().cloned());
+            }
+        }
+
+        let levels = self
+            .levels
+            .into_iter()
+            .map(|level| {
+                level
+                    .into_iter()
+                    .filter(|tablet| needed.contains(&tablet.thing))
+                    .collect::<Vec<Tablet>>()
+            })
+            .filter(|level| !level.is_empty())
+            .collect();
+
+        Strategy {
+            base: self.base,
+            levels,
+        }
+    }
+}
+
+// matchers of the query with the branch they belong to
+// and the json path of the leaf they are at
+fn gather_matchers(entry: &Entry, path: &str, matchers: &mut Vec<(String, Matcher, String)>) {
+    if let Some(m) = entry.value_matcher() {
+        matchers.push((entry.base.to_owned(), m, format!("{}.{}", path, entry.base)));
+    }
+
+    for (leaf, items) in entry.leaves.iter() {
+        for (i, item) in items.iter().enumerate() {
+            let path_leaf = match items.len() {
+                1 => format!("{}.{}", path, leaf),
+                _ => format!("{}.{}[{}]", path, leaf, i),
+            };
+
+            match item.value_matcher() {
+                Some(m) if item.leaves.is_empty() => matchers.push((leaf.to_owned(), m, path_leaf)),
+                _ => gather_matchers(item, &path_leaf, matchers),
+            }
+        }
+    }
+}
+
+// compile each matcher of the query once for the tablets that match it,
+// and an empty pattern for grains without a value
+fn compile_patterns(schema: &Schema, query: &Entry, tablets: &mut [Tablet]) -> Result<()> {
+    let mut matchers = vec![];
+
+    gather_matchers(query, "$", &mut matchers);
+
+    let mut patterns: HashMap<String, HashMap<Matcher, Pattern>> = HashMap::new();
+
+    for (branch, matcher, path) in matchers {
+        let patterns_branch = patterns.entry(branch.to_owned()).or_default();
+
+        if patterns_branch.contains_key(&matcher) {
+            continue;
+        }
+
+        let pattern = matcher
+            .compile_as(schema.value_type(&branch))
+            .map_err(|e| Error::invalid_query(path, e))?;
 
-    let strategy_base = if !strategy_query.is_empty() {
+        patterns_branch.insert(matcher, pattern);
+    }
+
+    for tablet in tablets.iter_mut().filter(|tablet| tablet.trait_is_regex) {
+        tablet.patterns = patterns.get(&tablet.trait_).cloned().unwrap_or_default();
+
+        let matcher = Matcher::regex("");
+
+        let pattern = matcher
+            .compile_as(tablet.trait_type)
+            .map_err(|e| Error::invalid_query("$", e))?;
+
+        tablet.patterns.insert(matcher, pattern);
+    }
+
+    Ok(())
+}
+
+pub fn plan_select(schema: &Schema, query: &Entry, indexes: &[String]) -> Result<Strategy> {
+    let strategy_query = plan_query(schema, query, indexes);
+
+    let mut strategy_base = if !strategy_query.is_empty() {
         strategy_query
     } else {
         plan_options(schema, &query.base)
     };
 
-    let strategy_value = plan_values(schema, query);
+    compile_patterns(schema, query, &mut strategy_base)?;
+
+    // a value tablet reads values of its trunk,
+    // which tablets of lower nesting levels find
+    let mut levels: Vec<(i32, Vec<Tablet>)> = vec![];
+
+    for tablet in plan_values(schema, query) {
+        let level = schema.get_nesting_level(&tablet.thing);
+
+        match levels.last_mut() {
+            Some((l, tablets)) if *l == level => tablets.push(tablet),
+            _ => levels.push((level, vec![tablet])),
+        }
+    }
+
+    Ok(Strategy {
+        base: strategy_base,
+        levels: levels.into_iter().map(|(_, tablets)| tablets).collect(),
+    })
+}
+
+fn negative_matcher(entry: &Entry) -> Option<Matcher> {
+    entry.base_matcher.clone().filter(|m| m.is_negative())
+}
+
+fn make_filter(schema: &Schema, branch: &str, matchers: Vec<Matcher>) -> Result<Filter> {
+    let patterns = matchers
+        .iter()
+        .map(|matcher| {
+            matcher
+                .compile_as(schema.value_type(branch))
+                .map_err(|e| Error::invalid_query(format!("$.{}", branch), e))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Filter {
+        branch: branch.to_owned(),
+        patterns,
+    })
+}
+
+// move negations out of the query, tablets can only find lines that match
+fn split_negative(schema: &Schema, entry: &Entry, filters: &mut Vec<Filter>) -> Result<Entry> {
+    let mut positive = entry.clone();
+
+    if let Some(m) = negative_matcher(entry) {
+        filters.push(make_filter(schema, &entry.base, vec![m])?);
+
+        positive.base_matcher = None;
+    }
+
+    positive.leaves = HashMap::new();
+
+    for (leaf, items) in entry.leaves.iter() {
+        if leaf == "$or" {
+            return Err(Error::invalid_query(
+                format!("$.{}.$or", entry.base),
+                "$or is only allowed at the base of a query",
+            ));
+        }
+
+        let mut items_positive = vec![];
+
+        for item in items {
+            if !item.leaves.is_empty() {
+                items_positive.push(split_negative(schema, item, filters)?);
+
+                continue;
+            }
+
+            match negative_matcher(item) {
+                Some(m) => filters.push(make_filter(schema, leaf, vec![m])?),
+                None => items_positive.push(item.clone()),
+            }
+        }
+
+        if !items_positive.is_empty() {
+            positive.leaves.insert(leaf.to_owned(), items_positive);
+        }
+    }
+
+    Ok(positive)
+}
+
+/// entry whose base values must match both matchers
+pub fn merge_matcher(entry: &mut Entry, matcher: Option<Matcher>) {
+    let operators = [entry.value_matcher(), matcher]
+        .into_iter()
+        .flatten()
+        .flat_map(|m| m.0)
+        .collect::<Vec<_>>();
+
+    if !operators.is_empty() {
+        entry.base_value = None;
+
+        entry.base_matcher = Some(Matcher(operators));
+    }
+}
+
+/// queries without $or, one for each group of the query,
+/// each with the filters of its negations
+pub fn plan_groups(schema: &Schema, query: &Entry) -> Result<Vec<(Entry, Vec<Filter>)>> {
+    let mut query_common = query.clone();
+
+    let groups = query_common.leaves.remove("$or").unwrap_or_default();
+
+    if groups.is_empty() {
+        let mut filters = vec![];
+
+        let positive = split_negative(schema, &query_common, &mut filters)?;
+
+        // tablets find entries by their leaves, so a base value
+        // next to leaves is checked on the found entries
+        match positive.value_matcher() {
+            Some(matcher) if !positive.leaves.is_empty() => {
+                filters.push(make_filter(schema, &positive.base, vec![matcher])?);
+            }
+            _ => (),
+        }
+
+        return Ok(vec![(positive, filters)]);
+    }
+
+    let mut queries = vec![];
+
+    for group in groups {
+        let mut query_group = query_common.clone();
+
+        merge_matcher(&mut query_group, group.value_matcher());
+
+        let mut filters_group = vec![];
+
+        for (leaf, items) in group.leaves {
+            if !query_group.leaves.contains_key(&leaf) {
+                query_group.leaves.insert(leaf, items);
+
+                continue;
+            }
+
+            // words of $text must all match
+            if leaf == TEXT {
+                query_group.leaves.entry(leaf).or_default().extend(items);
+
+                continue;
+            }
+
+            // items of a leaf are alternatives, so a leaf
+            // that the query also has is checked on the found entries
+            if items.iter().any(|item| !item.leaves.is_empty()) {
+                return Err(Error::invalid_query(
+                    format!("$.$or.{}", leaf),
+                    "nested leaf is also outside of $or",
+                ));
+            }
+
+            let matchers = items
+                .iter()
+                .filter_map(|item| item.value_matcher())
+                .collect();
+
+            filters_group.push(make_filter(schema, &leaf, matchers)?);
+        }
+
+        // a group can have groups of its own
+        for (query_planned, filters) in plan_groups(schema, &query_group)? {
+            queries.push((query_planned, [filters, filters_group.clone()].concat()));
+        }
+    }
 
-    [strategy_base, strategy_value].concat()
+    Ok(queries)
 }
@@ -1,10 +1,13 @@
-use super::line::select_line_stream;
+use super::bisect::read_key;
+use super::line::{lookup_trait, select_line_stream};
+use crate::dataset::index::{index_name, is_fresh};
+use crate::dataset::tablets::{read_order, Order};
 use super::schema::select_schema_line_stream;
 use super::types::state::State;
 use super::types::tablet::Tablet;
 use crate::{line::Line, Entry, Error, Result};
 use async_stream::{stream, try_stream};
-use futures_core::stream::Stream;
+use futures_core::stream::{BoxStream, Stream};
 use futures_util::pin_mut;
 use futures_util::stream::StreamExt;
 use serde::{Deserialize, Serialize};
@@ -18,7 +21,7 @@ fn line_stream(filepath: PathBuf) -> impl Stream<Item = Result<Line>> {
             return;
         }
 
-        let file = File::open(filepath)?;
+        let file = File::open(&filepath)?;
 
         let mut rdr = csv::ReaderBuilder::new()
             .has_headers(false)
@@ -26,7 +29,7 @@ fn line_stream(filepath: PathBuf) -> impl Stream<Item = Result<Line>> {
             .from_reader(file);
 
         for result in rdr.records() {
-            let record = result?;
+            let record = result.map_err(|e| Error::from_csv(&filepath, e))?;
 
             let line = Line {
                 key: match record.get(0) { None => String::from(""), Some(s) => s.to_owned() },
@@ -38,14 +41,40 @@ fn line_stream(filepath: PathBuf) -> impl Stream<Item = Result<Line>> {
     }
 }
 
+// lines of one key found by bisecting a sorted tablet,
+// or every line of a tablet that might not be sorted
+fn key_stream(filepath: PathBuf, key: String, order: Order) -> impl Stream<Item = Result<Line>> {
+    try_stream! {
+        if order == Order::Unsorted {
+            for await line in line_stream(filepath) {
+                yield line?;
+            }
+
+            return;
+        }
+
+        if std::fs::metadata(&filepath).is_err() {
+            return;
+        }
+
+        for line in read_key(&filepath, &key, order)? {
+            yield line;
+        }
+    }
+}
+
 pub fn select_tablet<S: Stream<Item = Result<State>>>(
     path: PathBuf,
+    order: Order,
     tablet: Tablet,
     input: S,
 ) -> impl Stream<Item = Result<State>> {
     // println!("{}", serde_json::to_string_pretty(&tablet).expect(""));
     // println!("{}", tablet.filename);
 
+    // indexes are always written sorted
+    let index_order = read_order(&path);
+
     try_stream! {
         for await state in input {
             let state = state?;
@@ -107,7 +136,25 @@ pub fn select_tablet<S: Stream<Item = Result<State>>>(
                     continue;
                 }
 
-                let s = select_line_stream(line_stream(filepath), state, tablet.clone());
+                // a literal trait only needs its own lines,
+                // found by key in the tablet or by value in its reverse index
+                let trait_ = lookup_trait(&state, &tablet);
+
+                let is_indexed = tablet.reverse_index && is_fresh(&path, &tablet.filename)?;
+
+                let lines: BoxStream<'static, Result<Line>> = match trait_ {
+                    Some(key) if tablet.trait_is_first => Box::pin(key_stream(filepath, key, order)),
+                    Some(value) if is_indexed => {
+                        let index_path = path.join(index_name(&tablet.filename));
+
+                        Box::pin(key_stream(index_path, value, index_order).map(|line| {
+                            line.map(|l| Line { key: l.value, value: l.key })
+                        }))
+                    }
+                    _ => Box::pin(line_stream(filepath)),
+                };
+
+                let s = select_line_stream(lines, state, tablet.clone());
 
                 pin_mut!(s); // needed for iteration
 
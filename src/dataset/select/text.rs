@@ -0,0 +1,186 @@
+use super::bisect::read_prefix;
+use crate::dataset::index::{is_text_fresh, text_name};
+use crate::dataset::tablets::read_order;
+use crate::matcher::tokenize;
+use crate::{Branch, Entry, Error, Result, Schema, Trunks};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::Path;
+
+/// leaf of a query with words to find in any leaf of the entry
+pub const TEXT: &str = "$text";
+
+fn has_text(entry: &Entry) -> bool {
+    entry
+        .leaves
+        .iter()
+        .any(|(leaf, items)| leaf == TEXT || items.iter().any(has_text))
+}
+
+/// take the words of $text out of a query
+pub fn take_text(query: &mut Entry) -> Result<Vec<String>> {
+    for (leaf, items) in query.leaves.iter() {
+        if leaf != TEXT && items.iter().any(has_text) {
+            return Err(Error::invalid_query(
+                format!("$.{}.{}", leaf, TEXT),
+                "$text is only allowed at the base of a query",
+            ));
+        }
+    }
+
+    let items = match query.leaves.remove(TEXT) {
+        None => return Ok(vec![]),
+        Some(items) => items,
+    };
+
+    let mut tokens: Vec<String> = vec![];
+
+    for item in items {
+        for token in tokenize(item.base_value.as_deref().unwrap_or("")) {
+            if !tokens.contains(&token) {
+                tokens.push(token);
+            }
+        }
+    }
+
+    if tokens.is_empty() {
+        return Err(Error::invalid_query(format!("$.{}", TEXT), "expected words"));
+    }
+
+    Ok(tokens)
+}
+
+// tablets of the leaves of the base with the trunks they are read for
+fn text_tablets(schema: &Schema, base: &str) -> Vec<(String, String)> {
+    let crown = schema.find_crown(base);
+
+    let mut tablets = vec![];
+
+    for branch in crown.iter().filter(|b| *b != base) {
+        if let Some(Branch { trunks: Trunks(ts), .. }) = schema.0.get(branch) {
+            for trunk in ts.iter().filter(|t| crown.contains(t)) {
+                tablets.push((trunk.to_owned(), format!("{}-{}.csv", trunk, branch)));
+            }
+        }
+    }
+
+    tablets
+}
+
+// for each token, values of each trunk that have a word starting with it
+fn find_tokens(dir: &Path, schema: &Schema, base: &str, tokens: &[String]) -> Result<Vec<HashMap<String, HashSet<String>>>> {
+    let mut found: Vec<HashMap<String, HashSet<String>>> = vec![HashMap::new(); tokens.len()];
+
+    for (trunk, filename) in text_tablets(schema, base) {
+        let filepath = dir.join(&filename);
+
+        if std::fs::metadata(&filepath).is_err() {
+            continue;
+        }
+
+        if is_text_fresh(dir, &filename)? {
+            for (i, token) in tokens.iter().enumerate() {
+                for line in read_prefix(&dir.join(text_name(&filename)), token, read_order(dir))? {
+                    found[i].entry(trunk.to_owned()).or_default().insert(line.value);
+                }
+            }
+
+            continue;
+        }
+
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(File::open(&filepath)?);
+
+        for result in rdr.records() {
+            let record = result.map_err(|e| Error::from_csv(&filepath, e))?;
+
+            let words = tokenize(record.get(1).unwrap_or(""));
+
+            for (i, token) in tokens.iter().enumerate() {
+                if words.iter().any(|word| word.starts_with(token.as_str())) {
+                    let key = record.get(0).unwrap_or("").to_owned();
+
+                    found[i].entry(trunk.to_owned()).or_default().insert(key);
+                }
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+// follow values of nested branches through their trunks down to the base
+fn lift(dir: &Path, schema: &Schema, base: &str, found: HashMap<String, HashSet<String>>) -> Result<HashSet<String>> {
+    let crown = schema.find_crown(base);
+
+    let mut pending = found;
+
+    loop {
+        let branch = pending
+            .keys()
+            .filter(|b| *b != base)
+            .max_by_key(|b| schema.get_nesting_level(b))
+            .cloned();
+
+        let branch = match branch {
+            None => break,
+            Some(b) => b,
+        };
+
+        let values = pending.remove(&branch).unwrap_or_default();
+
+        let trunks = match schema.0.get(&branch) {
+            None => vec![],
+            Some(Branch { trunks: Trunks(ts), .. }) => ts.to_vec(),
+        };
+
+        for trunk in trunks.iter().filter(|t| crown.contains(t)) {
+            let filepath = dir.join(format!("{}-{}.csv", trunk, branch));
+
+            if std::fs::metadata(&filepath).is_err() {
+                continue;
+            }
+
+            let mut rdr = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .from_reader(File::open(&filepath)?);
+
+            for result in rdr.records() {
+                let record = result.map_err(|e| Error::from_csv(&filepath, e))?;
+
+                if values.contains(record.get(1).unwrap_or("")) {
+                    let key = record.get(0).unwrap_or("").to_owned();
+
+                    pending.entry(trunk.to_owned()).or_default().insert(key);
+                }
+            }
+        }
+    }
+
+    Ok(pending.remove(base).unwrap_or_default())
+}
+
+/// sorted values of the base that have every token
+/// at the start of a word in one of their leaves,
+/// reads text indexes of tablets that have a fresh one
+pub fn search_text(dir: &Path, schema: &Schema, base: &str, tokens: &[String]) -> Result<Vec<String>> {
+    let mut base_values: Option<HashSet<String>> = None;
+
+    for found in find_tokens(dir, schema, base, tokens)? {
+        let values = lift(dir, schema, base, found)?;
+
+        base_values = Some(match base_values {
+            None => values,
+            Some(vs) => vs.intersection(&values).cloned().collect(),
+        });
+    }
+
+    let mut base_values: Vec<String> = base_values.unwrap_or_default().into_iter().collect();
+
+    base_values.sort();
+
+    Ok(base_values)
+}
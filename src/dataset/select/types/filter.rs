@@ -0,0 +1,37 @@
+use crate::matcher::Pattern;
+use crate::Entry;
+
+/// leaf of a query that is decided on all values of its branch in a found entry,
+/// like {"$not": ..} and {"$exists": false}, one of the patterns must match
+#[derive(Debug, Clone)]
+pub struct Filter {
+    pub branch: String,
+    pub patterns: Vec<Pattern>,
+}
+
+/// values of the branch anywhere in the entry
+pub fn branch_values(entry: &Entry, branch: &str) -> Vec<String> {
+    let mut values = vec![];
+
+    if entry.base == branch {
+        if let Some(v) = &entry.base_value {
+            values.push(v.to_owned());
+        }
+    }
+
+    for items in entry.leaves.values() {
+        for item in items {
+            values.extend(branch_values(item, branch));
+        }
+    }
+
+    values
+}
+
+impl Filter {
+    pub fn is_match(&self, entry: &Entry) -> bool {
+        let values = branch_values(entry, &self.branch);
+
+        self.patterns.iter().any(|pattern| pattern.is_match_any(&values))
+    }
+}
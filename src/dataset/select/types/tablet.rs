@@ -1,4 +1,7 @@
+use crate::matcher::Pattern;
+use crate::{Matcher, ValueType};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Tablet {
@@ -13,4 +16,12 @@ pub struct Tablet {
     pub querying: bool,
     pub eager: bool,
     pub accumulating: bool,
+    /// value,key copy of the tablet under .csvs/index
+    pub reverse_index: bool,
+    /// declared type of trait values, which patterns compare in
+    #[serde(default)]
+    pub trait_type: ValueType,
+    /// compiled matchers of the trait in the query
+    #[serde(skip)]
+    pub patterns: HashMap<Matcher, Pattern>,
 }
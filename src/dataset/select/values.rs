@@ -0,0 +1,211 @@
+use super::bisect::read_key;
+use crate::dataset::tablets::Order;
+use super::line::{select_line_stream, value_traits};
+use super::types::state::State;
+use super::types::tablet::Tablet;
+use crate::{line::Line, Error, Result};
+use async_stream::try_stream;
+use futures_core::stream::Stream;
+use futures_util::future::join_all;
+use futures_util::pin_mut;
+use futures_util::stream::{self, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+// bisect a sorted tablet for at most this many traits
+const BISECT_KEYS: usize = 64;
+
+// lines with one of the keys and their line numbers,
+// with the line after each run of them
+// so that a group still ends where it ends in the tablet
+fn scan_tablet(
+    filepath: &Path,
+    keys: &HashSet<String>,
+    order: Order,
+) -> Result<Vec<(usize, Line)>> {
+    if keys.is_empty() || std::fs::metadata(filepath).is_err() {
+        return Ok(vec![]);
+    }
+
+    if keys.len() <= BISECT_KEYS && order != Order::Unsorted {
+        let mut keys_sorted: Vec<&String> = keys.iter().collect();
+
+        keys_sorted.sort();
+
+        let mut lines = vec![];
+
+        for key in keys_sorted {
+            lines.extend(read_key(filepath, key, order)?);
+        }
+
+        return Ok(lines.into_iter().enumerate().collect());
+    }
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(File::open(filepath)?);
+
+    let mut lines = vec![];
+
+    let mut is_run = false;
+
+    for (n, result) in rdr.records().enumerate() {
+        let record = result.map_err(|e| Error::from_csv(filepath, e))?;
+
+        let line = Line {
+            key: match record.get(0) { None => String::from(""), Some(s) => s.to_owned() },
+            value: match record.get(1) { None => String::from(""), Some(s) => s.to_owned() },
+        };
+
+        let is_key = keys.contains(&line.key);
+
+        if is_key || is_run {
+            lines.push((n, line));
+        }
+
+        is_run = is_key;
+    }
+
+    Ok(lines)
+}
+
+// read every tablet of a level on its own task
+async fn scan_level(
+    path: &Path,
+    order: Order,
+    level: &[Tablet],
+    states: &[State],
+) -> Result<Vec<Vec<(usize, Line)>>> {
+    let handles = level.iter().map(|tablet| {
+        let keys: HashSet<String> = states
+            .iter()
+            .flat_map(|state| value_traits(state, tablet))
+            .collect();
+
+        let filepath = path.join(&tablet.filename);
+
+        tokio::task::spawn_blocking(move || scan_tablet(&filepath, &keys, order))
+    });
+
+    let mut level_lines = vec![];
+
+    for handle in join_all(handles).await {
+        level_lines.push(handle.map_err(Error::from_message)??);
+    }
+
+    Ok(level_lines)
+}
+
+// lines of each key, each run followed by the line that ended it
+fn group_lines(lines: Vec<(usize, Line)>) -> HashMap<String, Vec<(usize, Line)>> {
+    let mut groups: HashMap<String, Vec<(usize, Line)>> = HashMap::new();
+
+    let mut run: Option<(usize, String)> = None;
+
+    for (n, line) in lines {
+        if let Some((m, key)) = run.take() {
+            if m + 1 == n && key != line.key {
+                groups.entry(key).or_default().push((n, line.clone()));
+            }
+        }
+
+        run = Some((n, line.key.to_owned()));
+
+        groups.entry(line.key.to_owned()).or_default().push((n, line));
+    }
+
+    groups
+}
+
+// lines of the state's traits in the order of the tablet
+fn state_lines(state: &State, tablet: &Tablet, groups: &HashMap<String, Vec<(usize, Line)>>) -> Vec<Result<Line>> {
+    let mut lines_state: Vec<&(usize, Line)> = value_traits(state, tablet)
+        .iter()
+        .filter_map(|key| groups.get(key))
+        .flatten()
+        .collect();
+
+    // traits of a state can share the line that ends a run
+    lines_state.sort_by_key(|(n, _)| *n);
+
+    lines_state.dedup_by_key(|(n, _)| *n);
+
+    lines_state.into_iter().map(|(_, line)| Ok(line.clone())).collect()
+}
+
+async fn select_batch(
+    path: &Path,
+    order: Order,
+    levels: &[Vec<Tablet>],
+    states: Vec<State>,
+) -> Result<Vec<State>> {
+    let mut states = states;
+
+    for level in levels {
+        let level_lines = scan_level(path, order, level, &states).await?;
+
+        // tablets of a level don't read each other's values,
+        // but keep their order for the leader of the query
+        for (tablet, lines) in level.iter().zip(level_lines) {
+            let groups = group_lines(lines);
+
+            let mut states_new = vec![];
+
+            for state in states {
+                let lines_state = state_lines(&state, tablet, &groups);
+
+                let s = select_line_stream(stream::iter(lines_state), state, tablet.clone());
+
+                pin_mut!(s); // needed for iteration
+
+                while let Some(state) = s.next().await {
+                    states_new.push(state?);
+                }
+            }
+
+            states = states_new;
+        }
+    }
+
+    Ok(states)
+}
+
+/// find values of entries, reading value tablets
+/// once for each batch of entries, bisecting them if sorted
+pub fn select_values_stream<S: Stream<Item = Result<State>>>(
+    path: PathBuf,
+    order: Order,
+    levels: Vec<Vec<Tablet>>,
+    batch_size: usize,
+    input: S,
+) -> impl Stream<Item = Result<State>> {
+    try_stream! {
+        let mut batch = vec![];
+
+        for await state in input {
+            let state = state?;
+
+            // value tablets receive a matchMap from accumulating tablets
+            // but don't need to do anything with it or with the accompanying entry
+            if state.match_map.is_some() {
+                continue;
+            }
+
+            batch.push(state);
+
+            if batch.len() == batch_size {
+                for state in select_batch(&path, order, &levels, std::mem::take(&mut batch)).await? {
+                    yield state;
+                }
+            }
+        }
+
+        if !batch.is_empty() {
+            for state in select_batch(&path, order, &levels, batch).await? {
+                yield state;
+            }
+        }
+    }
+}
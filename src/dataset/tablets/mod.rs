@@ -0,0 +1,155 @@
+use super::types::TYPES;
+use crate::version::CSVS_VERSION;
+use crate::{line::Line, Error, Result};
+use std::fs;
+use std::fs::File;
+use std::path::Path;
+
+/// key,value lines of a csv file, none if the file does not exist
+pub fn read_lines(filepath: &Path) -> Result<Vec<Line>> {
+    if fs::metadata(filepath).is_err() {
+        return Ok(vec![]);
+    }
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(File::open(filepath)?);
+
+    let mut lines = vec![];
+
+    for result in rdr.records() {
+        let record = result.map_err(|e| Error::from_csv(filepath, e))?;
+
+        lines.push(Line {
+            key: match record.get(0) { None => String::from(""), Some(s) => s.to_owned() },
+            value: match record.get(1) { None => String::from(""), Some(s) => s.to_owned() },
+        });
+    }
+
+    Ok(lines)
+}
+
+/// marks a dataset whose tablets are all sorted by key, then value,
+/// every write keeps them so once it is there
+pub const SORTED: &str = ".csvs/sorted";
+
+/// marks a dataset that has a value with a line break,
+/// so that a line of a tablet might not start a record
+pub const MULTILINE: &str = ".csvs/multiline";
+
+/// how the lines of a tablet are laid out, so that select knows if it can bisect them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// read every line
+    Unsorted,
+    /// sorted, each line is a record
+    Lines,
+    /// sorted, a record might span lines
+    Records,
+}
+
+/// order of sorted files like indexes in the dataset
+pub fn read_order(dir: &Path) -> Order {
+    match fs::metadata(dir.join(MULTILINE)) {
+        Ok(_) => Order::Records,
+        Err(_) => Order::Lines,
+    }
+}
+
+/// order of the tablets in the dataset
+pub fn read_tablet_order(dir: &Path) -> Order {
+    match fs::metadata(dir.join(SORTED)) {
+        Ok(_) => read_order(dir),
+        Err(_) => Order::Unsorted,
+    }
+}
+
+/// write a marker with the version that wrote it
+pub fn write_marker(filepath: &Path) -> Result<()> {
+    fs::write(filepath, CSVS_VERSION.to_string())?;
+
+    Ok(())
+}
+
+/// a key or a value of the csv file has a line break
+pub fn has_multiline(filepath: &Path) -> Result<bool> {
+    if fs::metadata(filepath).is_err() {
+        return Ok(false);
+    }
+
+    let bytes = fs::read(filepath)?;
+
+    // a line break is quoted, so a file without quotes has none
+    if !bytes.contains(&b'"') {
+        return Ok(false);
+    }
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(&bytes[..]);
+
+    for result in rdr.records() {
+        let record = result.map_err(|e| Error::from_csv(filepath, e))?;
+
+        if record.iter().any(|field| field.contains('\n')) {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// sort by key, then value, the order that select bisects on
+pub fn sort_lines(lines: &mut [Line]) {
+    lines.sort_by(|a, b| a.key.cmp(&b.key).then(a.value.cmp(&b.value)));
+}
+
+/// sort the key,value lines of a csv file in place
+pub fn sort_file(filepath: &Path) -> Result<()> {
+    let mut lines = read_lines(filepath)?;
+
+    sort_lines(&mut lines);
+
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_path(filepath)?;
+
+    for line in lines {
+        wtr.serialize(line)?;
+    }
+
+    wtr.flush()?;
+
+    Ok(())
+}
+
+/// sorted filenames of trunk-leaf tablets in the dataset directory,
+/// without the schema, the types and dot files like .csvs.csv
+pub fn list_tablets(dir: &Path) -> Result<Vec<String>> {
+    let mut filenames = vec![];
+
+    for file_entry in fs::read_dir(dir)? {
+        let file_entry = file_entry?;
+
+        if !file_entry.file_type()?.is_file() {
+            continue;
+        }
+
+        let filename = file_entry.file_name().to_string_lossy().to_string();
+
+        let is_tablet = filename.ends_with(".csv")
+            && !filename.starts_with('.')
+            && filename != "_-_.csv"
+            && filename != TYPES;
+
+        if is_tablet {
+            filenames.push(filename);
+        }
+    }
+
+    filenames.sort();
+
+    Ok(filenames)
+}
@@ -0,0 +1,421 @@
+use super::index::{
+    index_name, stamp_name, text_name, write_index, write_stamp, write_text_index, INDEX_DIR,
+    TEXT_DIR,
+};
+use super::tablets::{has_multiline, read_lines, write_marker, MULTILINE};
+use crate::{line::Line, Error, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::fs::{File, TryLockError};
+use std::path::{Path, PathBuf};
+
+// every staged tablet is listed in the journal before any of them
+// is moved into the dataset, a journal on disk means the commit must finish
+const JOURNAL: &str = "journal.csv";
+
+const STAGE: &str = "stage";
+
+fn meta_dir(dir: &Path) -> PathBuf {
+    dir.join(".csvs")
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<()> {
+    File::open(dir)?.sync_all()?;
+
+    Ok(())
+}
+
+// directories can't be opened for sync on other platforms
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<()> {
+    Ok(())
+}
+
+// a writer holds the lock of the dataset directory until its transaction ends,
+// so that another writer waits rather than cleans the stage in use
+struct Lock {
+    _file: Option<File>,
+}
+
+// none if another writer holds the lock and wait is false
+#[cfg(unix)]
+fn lock(dir: &Path, wait: bool) -> Result<Option<Lock>> {
+    let file = File::open(dir)?;
+
+    if wait {
+        file.lock()?;
+    } else {
+        match file.try_lock() {
+            Ok(()) => (),
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+    }
+
+    Ok(Some(Lock { _file: Some(file) }))
+}
+
+// directories can't be opened for a lock on other platforms
+#[cfg(not(unix))]
+fn lock(_dir: &Path, _wait: bool) -> Result<Option<Lock>> {
+    Ok(Some(Lock { _file: None }))
+}
+
+fn sync_file(filepath: &Path) -> Result<()> {
+    File::open(filepath)?.sync_all()?;
+
+    Ok(())
+}
+
+// move staged tablets into the dataset, safe to repeat after a crash
+fn apply(dir: &Path, lines: &[Line]) -> Result<()> {
+    let stage = meta_dir(dir).join(STAGE);
+
+    for line in lines {
+        let staged = stage.join(&line.value);
+
+        let filepath = dir.join(&line.value);
+
+        match line.key.as_str() {
+            "write" => {
+                // already moved by an interrupted commit
+                if fs::metadata(&staged).is_ok() {
+                    // first reverse index creates its directory
+                    if let Some(parent) = filepath.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+
+                    fs::rename(&staged, &filepath)?;
+                }
+            }
+            "remove" => {
+                if fs::metadata(&filepath).is_ok() {
+                    fs::remove_file(&filepath)?;
+                }
+            }
+            op => {
+                return Err(Error::from_message(format!(
+                    "unknown journal operation {} for {}",
+                    op, line.value
+                )))
+            }
+        }
+    }
+
+    sync_dir(dir)
+}
+
+fn clean(dir: &Path) -> Result<()> {
+    let meta = meta_dir(dir);
+
+    // stage goes after the journal, a crash in between leaves nothing to replay
+    if fs::metadata(meta.join(JOURNAL)).is_ok() {
+        fs::remove_file(meta.join(JOURNAL))?;
+    }
+
+    if fs::metadata(meta.join(STAGE)).is_ok() {
+        fs::remove_dir_all(meta.join(STAGE))?;
+    }
+
+    // journal of a commit that crashed before its rename
+    if fs::metadata(meta.join(format!("{}.tmp", JOURNAL))).is_ok() {
+        fs::remove_file(meta.join(format!("{}.tmp", JOURNAL)))?;
+    }
+
+    // keep .csvs if anything else lives there
+    if fs::metadata(&meta).is_ok() && fs::read_dir(&meta)?.next().is_none() {
+        fs::remove_dir(&meta)?;
+    }
+
+    Ok(())
+}
+
+// finish a commit that has a journal, discard staged tablets that don't,
+// only while holding the lock
+fn recover_locked(dir: &Path) -> Result<()> {
+    let journal = meta_dir(dir).join(JOURNAL);
+
+    if fs::metadata(&journal).is_ok() {
+        apply(dir, &read_lines(&journal)?)?;
+    }
+
+    clean(dir)
+}
+
+/// finish an interrupted commit before reading tablets,
+/// and discard what a crashed writer staged unless another writer is active
+pub fn recover(dir: &Path) -> Result<()> {
+    let meta = meta_dir(dir);
+
+    let is_journaled = fs::metadata(meta.join(JOURNAL)).is_ok();
+
+    if !is_journaled && fs::metadata(meta.join(STAGE)).is_err() {
+        return Ok(());
+    }
+
+    // an active writer owns its stage, but a journal must be applied before reads
+    let lock = match lock(dir, false)? {
+        None if is_journaled => lock(dir, true)?,
+        l => l,
+    };
+
+    if lock.is_some() {
+        recover_locked(dir)?;
+    }
+
+    Ok(())
+}
+
+/// tablet rewrites of one mutation, written to the stage
+/// and moved into the dataset together on commit
+pub struct Transaction {
+    dir: PathBuf,
+    stage: PathBuf,
+    // tablets a dry run has edited so far, which commit moves there instead
+    dry: Option<PathBuf>,
+    staged: BTreeSet<String>,
+    is_done: bool,
+    // released after drop cleans the stage
+    _lock: Option<Lock>,
+}
+
+impl Transaction {
+    /// waits for the transaction of another writer to end
+    pub fn begin(dir: &Path) -> Result<Self> {
+        let lock = lock(dir, true)?;
+
+        // whatever a crashed writer left is not in use
+        recover_locked(dir)?;
+
+        fs::create_dir_all(meta_dir(dir).join(STAGE).join(INDEX_DIR))?;
+
+        fs::create_dir_all(meta_dir(dir).join(STAGE).join(TEXT_DIR))?;
+
+        Ok(Transaction {
+            dir: dir.to_path_buf(),
+            stage: meta_dir(dir).join(STAGE),
+            dry: None,
+            staged: BTreeSet::new(),
+            is_done: false,
+            _lock: lock,
+        })
+    }
+
+    /// stages in the dry directory and commits edited tablets there,
+    /// leaving the dataset as it is
+    pub fn begin_dry(dir: &Path, dry: &Path) -> Result<Self> {
+        fs::create_dir_all(dry.join(STAGE))?;
+
+        Ok(Transaction {
+            dir: dir.to_path_buf(),
+            stage: dry.join(STAGE),
+            dry: Some(dry.to_path_buf()),
+            staged: BTreeSet::new(),
+            is_done: false,
+            _lock: None,
+        })
+    }
+
+    /// path to write the new tablet to, a missing or empty file removes the tablet
+    pub fn stage(&mut self, filename: &str) -> PathBuf {
+        self.staged.insert(filename.to_owned());
+
+        self.stage.join(filename)
+    }
+
+    /// path to the new tablet that starts as a copy of the current one
+    pub fn stage_copy(&mut self, filename: &str) -> Result<PathBuf> {
+        let is_staged = self.staged.contains(filename);
+
+        let filepath = self.current(filename);
+
+        let staged = self.stage(filename);
+
+        if !is_staged && fs::metadata(&filepath).is_ok() {
+            fs::copy(filepath, &staged)?;
+        }
+
+        Ok(staged)
+    }
+
+    /// path to read the tablet as the transaction would leave it
+    pub fn current(&self, filename: &str) -> PathBuf {
+        if self.staged.contains(filename) {
+            return self.stage.join(filename);
+        }
+
+        match &self.dry {
+            Some(dry) if fs::metadata(dry.join(filename)).is_ok() => dry.join(filename),
+            _ => self.dir.join(filename),
+        }
+    }
+
+    pub fn staged(&self) -> Vec<PathBuf> {
+        self.staged
+            .iter()
+            .map(|filename| self.stage.join(filename))
+            .collect()
+    }
+
+    // keep edited tablets for the changeset, an empty file for a removed one,
+    // indexes follow the tablets and are not part of it
+    fn commit_dry(mut self, dry: &Path) -> Result<()> {
+        for filename in self
+            .staged
+            .iter()
+            .filter(|filename| !filename.contains('/'))
+        {
+            let staged = self.stage.join(filename);
+
+            if fs::metadata(&staged).is_ok() {
+                fs::rename(&staged, dry.join(filename))?;
+            } else {
+                File::create(dry.join(filename))?;
+            }
+        }
+
+        self.is_done = true;
+
+        fs::remove_dir_all(&self.stage)?;
+
+        Ok(())
+    }
+
+    pub fn commit(mut self) -> Result<()> {
+        if let Some(dry) = self.dry.clone() {
+            return self.commit_dry(&dry);
+        }
+
+        let meta = meta_dir(&self.dir);
+
+        let stage = self.stage.clone();
+
+        // rewrite the reverse and text indexes of every indexed tablet in the same commit
+        let tablets: Vec<String> = self
+            .staged
+            .iter()
+            .filter(|filename| !filename.contains('/'))
+            .cloned()
+            .collect();
+
+        let writers: [(fn(&str) -> String, fn(&Path, &Path) -> Result<()>); 2] =
+            [(index_name, write_index), (text_name, write_text_index)];
+
+        for filename in tablets {
+            for (name, write) in writers {
+                let index = name(&filename);
+
+                let is_indexed = fs::metadata(self.dir.join(&index)).is_ok();
+
+                if !is_indexed || self.staged.contains(&index) {
+                    continue;
+                }
+
+                let staged_tablet = stage.join(&filename);
+
+                let staged_index = self.stage(&index);
+
+                let staged_stamp = self.stage(&stamp_name(&index));
+
+                let is_empty = match fs::metadata(&staged_tablet) {
+                    Err(_) => true,
+                    Ok(m) => m.len() == 0,
+                };
+
+                if !is_empty {
+                    write(&staged_tablet, &staged_index)?;
+
+                    write_stamp(&staged_tablet, &staged_stamp)?;
+                }
+            }
+        }
+
+        // bisect reads records instead of lines once a value has a line break
+        if fs::metadata(self.dir.join(MULTILINE)).is_err() {
+            let mut is_multiline = false;
+
+            for filename in self
+                .staged
+                .iter()
+                .filter(|filename| !filename.contains('/'))
+            {
+                is_multiline = is_multiline || has_multiline(&stage.join(filename))?;
+            }
+
+            if is_multiline {
+                write_marker(&self.stage(MULTILINE))?;
+            }
+        }
+
+        let mut lines = vec![];
+
+        for filename in self.staged.iter() {
+            let staged = stage.join(filename);
+
+            let is_empty = match fs::metadata(&staged) {
+                Err(_) => true,
+                Ok(m) => m.len() == 0,
+            };
+
+            if is_empty {
+                lines.push(Line {
+                    key: "remove".to_owned(),
+                    value: filename.to_owned(),
+                });
+            } else {
+                sync_file(&staged)?;
+
+                lines.push(Line {
+                    key: "write".to_owned(),
+                    value: filename.to_owned(),
+                });
+            }
+        }
+
+        sync_dir(&stage)?;
+
+        // the rename of a complete journal is the commit point
+        let journal_temp = meta.join(format!("{}.tmp", JOURNAL));
+
+        let mut wtr = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_path(&journal_temp)?;
+
+        for line in lines.iter() {
+            wtr.serialize(line)?;
+        }
+
+        wtr.flush()?;
+
+        sync_file(&journal_temp)?;
+
+        fs::rename(&journal_temp, meta.join(JOURNAL))?;
+
+        sync_dir(&meta)?;
+
+        apply(&self.dir, &lines)?;
+
+        self.is_done = true;
+
+        clean(&self.dir)
+    }
+}
+
+impl Drop for Transaction {
+    // an abandoned transaction leaves the dataset as it was,
+    // unless its journal is written and the next open must finish it
+    fn drop(&mut self) {
+        let meta = meta_dir(&self.dir);
+
+        if self.is_done {
+            return;
+        }
+
+        if self.dry.is_some() {
+            let _ = fs::remove_dir_all(&self.stage);
+        } else if fs::metadata(meta.join(JOURNAL)).is_err() {
+            let _ = clean(&self.dir);
+        }
+    }
+}
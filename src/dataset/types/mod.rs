@@ -0,0 +1,112 @@
+use super::transaction::Transaction;
+use crate::{line::Line, Dataset, Error, Result, Schema, ValueType};
+use futures_util::pin_mut;
+use futures_util::stream::StreamExt;
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::path::Path;
+
+/// branch,type lines next to the schema
+pub const TYPES: &str = "_-type.csv";
+
+/// types declared for branches, none if the dataset has no _-type.csv
+pub fn read_types(dir: &Path) -> Result<BTreeMap<String, ValueType>> {
+    let filepath = dir.join(TYPES);
+
+    let mut types = BTreeMap::new();
+
+    if fs::metadata(&filepath).is_err() {
+        return Ok(types);
+    }
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(File::open(&filepath)?);
+
+    for result in rdr.records() {
+        let record = result.map_err(|e| Error::from_csv(&filepath, e))?;
+
+        let branch = record.get(0).unwrap_or("").to_owned();
+
+        let value_type = record
+            .get(1)
+            .unwrap_or("")
+            .parse::<ValueType>()
+            .map_err(|e| e.context(format!("type of {}", branch)))?;
+
+        types.insert(branch, value_type);
+    }
+
+    Ok(types)
+}
+
+/// schema with the declared types of its branches
+pub fn apply_types(schema: Schema, types: &BTreeMap<String, ValueType>) -> Schema {
+    let mut schema = schema;
+
+    for (branch, value_type) in types {
+        if let Some(b) = schema.0.get_mut(branch) {
+            b.value_type = Some(*value_type);
+        }
+    }
+
+    schema
+}
+
+/// declare the type of a branch after checking its values, or remove the declaration
+pub async fn set_type(dataset: Dataset, branch: &str, value_type: Option<ValueType>) -> Result<()> {
+    let schema = dataset.schema().await?;
+
+    if !schema.0.contains_key(branch) {
+        return Err(Error::unknown_branch(branch));
+    }
+
+    if let Some(t) = value_type {
+        let s = dataset.clone().select_options(branch, None);
+
+        pin_mut!(s); // needed for iteration
+
+        while let Some(option) = s.next().await {
+            let option = option?;
+
+            // an empty value stands for no value
+            if !option.value.is_empty() && !t.is_valid(&option.value) {
+                return Err(Error::invalid_value(branch, &option.value, t));
+            }
+        }
+    }
+
+    let mut types = read_types(&dataset.dir)?;
+
+    match value_type {
+        None => types.remove(branch),
+        Some(t) => types.insert(branch.to_owned(), t),
+    };
+
+    let mut transaction = Transaction::begin(&dataset.dir)?;
+
+    let staged = transaction.stage(TYPES);
+
+    // no declarations leave no file, same as any empty tablet
+    if !types.is_empty() {
+        let mut wtr = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_path(&staged)?;
+
+        for (branch, value_type) in types {
+            wtr.serialize(Line {
+                key: branch,
+                value: value_type.to_string(),
+            })?;
+        }
+
+        wtr.flush()?;
+    }
+
+    transaction.commit()?;
+
+    dataset.forget_schema()
+}
+
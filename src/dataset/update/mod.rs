@@ -9,7 +9,6 @@ use std::fs;
 use std::fs::File;
 use std::fs::OpenOptions;
 use std::path::PathBuf;
-use temp_dir::TempDir;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct Tablet {
@@ -24,15 +23,27 @@ struct State {
     is_match: bool,
 }
 
-fn plan_update(schema: &Schema, query: &Entry) -> Vec<Tablet> {
+fn plan_update(schema: &Schema, query: &Entry) -> Result<Vec<Tablet>> {
     let is_schema = query.base == "_";
 
     if is_schema {
-        return vec![Tablet {
+        let schema_new: Schema = query.clone().try_into()?;
+
+        let problems = schema_new.validate();
+
+        if !problems.is_empty() {
+            return Err(Error::invalid_schema(problems));
+        }
+
+        return Ok(vec![Tablet {
             filename: "_-_.csv".to_owned(),
             trunk: "_".to_owned(),
             branch: "_".to_owned(),
-        }];
+        }]);
+    }
+
+    if !schema.0.contains_key(&query.base) {
+        return Err(Error::unknown_branch(&query.base));
     }
 
     let crown = schema.find_crown(&query.base);
@@ -57,7 +68,7 @@ fn plan_update(schema: &Schema, query: &Entry) -> Vec<Tablet> {
         [with_branch, tablets_new].concat()
     });
 
-    tablets
+    Ok(tablets)
 }
 
 fn update_schema_line_stream(entry: Entry) -> impl Stream<Item = Result<Line>> {
@@ -231,7 +242,8 @@ fn update_line_stream<S: Stream<Item = Result<Line>>>(
 
 fn line_stream(filepath: PathBuf) -> impl Stream<Item = Result<Line>> {
     try_stream! {
-        if fs::metadata(&filepath).is_err() { File::create(&filepath)?; }
+        // a missing tablet has no lines
+        if fs::metadata(&filepath).is_err() { return; }
 
         let mut rdr = csv::ReaderBuilder::new()
             .has_headers(false)
@@ -239,7 +251,7 @@ fn line_stream(filepath: PathBuf) -> impl Stream<Item = Result<Line>> {
             .from_reader(File::open(&filepath)?);
 
         for result in rdr.records() {
-            let record = result?;
+            let record = result.map_err(|e| Error::from_csv(&filepath, e))?;
 
             let line = Line {
                 key: match record.get(0) { None => String::from(""), Some(s) => s.to_owned() },
@@ -251,38 +263,26 @@ fn line_stream(filepath: PathBuf) -> impl Stream<Item = Result<Line>> {
     }
 }
 
+// reads the tablet at filepath and writes the updated lines to staged
 fn update_tablet<S: Stream<Item = Result<Entry>>>(
-    path: PathBuf,
+    filepath: PathBuf,
+    staged: PathBuf,
     tablet: Tablet,
     input: S,
 ) -> impl Stream<Item = Result<Entry>> {
-    let filepath = path.join(&tablet.filename);
-
     let is_schema = tablet.filename == "_-_.csv";
 
     try_stream! {
-        // must assign a variable to create the directory
-        // must assign inside the stream scope to keep the directory
-        let temp_d = TempDir::new()?;
-
-        // wrap in result here for try_stream! proc to pick up error from ?
-        let filename = match filepath.file_name() {
-            None => Err(Error::from_message("unexpected missing filename")),
-            Some(s) => Ok(s)
-        }?;
-
-        let temp_path = temp_d.as_ref().join(filename);
+        File::create(&staged)?;
 
-        File::create(&temp_path)?;
-
-        let temp_file = OpenOptions::new()
+        let staged_file = OpenOptions::new()
             .append(true)
-            .open(&temp_path)
+            .open(&staged)
             .expect("cannot open file");
 
         let mut wtr = csv::WriterBuilder::new()
             .has_headers(false)
-            .from_writer(temp_file);
+            .from_writer(staged_file);
 
         for await entry in input {
             let entry = entry?;
@@ -317,26 +317,6 @@ fn update_tablet<S: Stream<Item = Result<Entry>>>(
                 }
             }
         }
-
-        match fs::metadata(&temp_path) {
-            Err(_) => (),
-            Ok(m) => if m.len() == 0 {
-                if fs::metadata(&filepath).is_ok() {
-                    fs::remove_file(&filepath)?;
-                }
-            } else {
-                // fs::rename fails with invalid cross-device link
-                fs::copy(temp_path, &filepath)?;
-            }
-        }
-
-        match fs::metadata(&filepath) {
-            Err(_) => return,
-            Ok(m) => if m.len() == 0 {
-                fs::remove_file(filepath)?;
-            }
-        }
-
     }
 }
 
@@ -345,12 +325,25 @@ pub fn update_record_stream<S: Stream<Item = Result<Entry>>>(
     input: S,
 ) -> impl Stream<Item = Result<Entry>> {
     try_stream! {
-        let schema = dataset.clone().select_schema().await?;
-
         for await query in input {
             let query = query?;
 
-            let strategy = plan_update(&schema, &query);
+            let is_schema = query.base == "_";
+
+            // a previous query might have updated the schema,
+            // and a schema that is not valid can still be replaced
+            let schema = if is_schema {
+                Schema(HashMap::new())
+            } else {
+                dataset.schema().await?
+            };
+
+            let strategy = plan_update(&schema, &query)?;
+
+            schema.validate_values(&query)?;
+
+            // each query commits on its own, the next query reads its tablets
+            let mut transaction = dataset.begin()?;
 
             let query_stream = try_stream! {
                 yield query;
@@ -359,12 +352,26 @@ pub fn update_record_stream<S: Stream<Item = Result<Entry>>>(
             let mut stream: BoxStream<'static, Result<Entry>> = Box::pin(query_stream);
 
             for tablet in strategy {
-                stream = Box::pin(update_tablet(dataset.dir.clone(), tablet, stream));
+                let filepath = transaction.current(&tablet.filename);
+
+                let staged = transaction.stage(&tablet.filename);
+
+                stream = Box::pin(update_tablet(filepath, staged, tablet, stream));
             }
 
+            let mut entries = vec![];
+
             for await entry in stream {
-                let entry = entry?;
+                entries.push(entry?);
+            }
+
+            transaction.commit()?;
 
+            if is_schema {
+                dataset.forget_schema()?;
+            }
+
+            for entry in entries {
                 yield entry;
             }
         }
@@ -382,7 +389,9 @@ pub async fn update_record(dataset: Dataset, query: Vec<Entry>) -> Result<()> {
 
     pin_mut!(s); // needed for iteration
 
-    while let Some(entry) = s.next().await {}
+    while let Some(entry) = s.next().await {
+        entry?;
+    }
 
     Ok(())
 }
@@ -1,47 +1,60 @@
-use crate::{Entry, IntoValue};
+use crate::{Entry, IntoValue, Schema};
 use serde_json::{json, Value};
 
-impl IntoValue for Entry {
-    fn into_value(self) -> Value {
-        let mut value: Value = json!({
-            "_": self.base,
-        });
+// json of an entry with each value rendered by the branch it belongs to
+fn entry_value(entry: &Entry, render: &dyn Fn(&str, &str) -> Value) -> Value {
+    let mut value: Value = json!({
+        "_": entry.base,
+    });
 
-        match self.base_value {
-            None => (),
-            Some(s) => value[self.base] = s.into(),
-        }
+    match (&entry.base_matcher, &entry.base_value) {
+        (Some(m), _) => value[&entry.base] = m.clone().into_value(),
+        (None, Some(s)) => value[&entry.base] = render(&entry.base, s),
+        (None, None) => (),
+    }
 
-        match self.leader_value {
-            None => (),
-            Some(s) => value["__"] = s.into(),
-        }
+    match &entry.leader_value {
+        None => (),
+        Some(s) => value["__"] = s.to_owned().into(),
+    }
 
-        for (leaf, items) in self.leaves.iter() {
-            for entry in items {
-                // condense entry to a string if it has no leaves
-                let leaf_value: Value = match entry.leaves.is_empty() {
-                    true => match &entry.base_value {
-                        None => continue,
-                        Some(s) => s.to_owned().into(),
-                    },
-                    false => entry.clone().into_value(),
-                };
-
-                value[&leaf] = match value.get(leaf) {
-                    None => leaf_value,
-                    Some(i) => match i {
-                        Value::Null => panic!("unreachable"),
-                        Value::Bool(_) => panic!("unreachable"),
-                        Value::Number(_) => panic!("unreachable"),
-                        Value::String(s) => vec![s.to_owned().into(), leaf_value].into(),
-                        Value::Object(o) => vec![o.clone().into(), leaf_value].into(),
-                        Value::Array(vs) => [&vs[..], &[leaf_value]].concat().into(),
-                    },
-                };
-            }
+    for (leaf, items) in entry.leaves.iter() {
+        for item in items {
+            // condense entry to a value if it has no leaves
+            let leaf_value: Value = match item.leaves.is_empty() {
+                true => match (&item.base_matcher, &item.base_value) {
+                    (Some(m), _) => m.clone().into_value(),
+                    (None, Some(s)) => render(leaf, s),
+                    (None, None) => continue,
+                },
+                false => entry_value(item, render),
+            };
+
+            value[leaf] = match value.get(leaf) {
+                None => leaf_value,
+                Some(i) => match i {
+                    Value::Null => panic!("unreachable"),
+                    Value::Array(vs) => [&vs[..], &[leaf_value]].concat().into(),
+                    v => vec![v.clone(), leaf_value].into(),
+                },
+            };
         }
+    }
+
+    value
+}
+
+impl IntoValue for Entry {
+    fn into_value(self) -> Value {
+        entry_value(&self, &|_, s| s.into())
+    }
+}
 
-        value
+impl Entry {
+    /// json with values of typed branches as numbers and booleans,
+    /// apart from IntoValue because types are declared in the schema of a dataset,
+    /// TryFrom<Value> reads the numbers and booleans back as they are stored
+    pub fn into_typed_value(self, schema: &Schema) -> Value {
+        entry_value(&self, &|branch, s| schema.value_type(branch).to_value(s))
     }
 }
@@ -4,6 +4,7 @@ pub mod sow;
 mod try_from;
 use crate::Grain;
 use crate::IntoValue;
+use crate::Matcher;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
@@ -12,6 +13,9 @@ use std::fmt;
 pub struct Entry {
     pub base: String,
     pub base_value: Option<String>,
+    /// operators of a query in place of a base value, like {"$prefix": "2001-"}
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_matcher: Option<Matcher>,
     pub leader_value: Option<String>,
     pub leaves: HashMap<String, Vec<Entry>>,
 }
@@ -30,4 +34,14 @@ impl Entry {
     pub fn sow(&self, grain: &Grain, trait_: &str, thing: &str) -> Entry {
         sow::sow(self, grain, trait_, thing)
     }
+
+    /// operators that values of the base must satisfy in a query,
+    /// a bare string is one regex
+    pub fn value_matcher(&self) -> Option<Matcher> {
+        match (&self.base_matcher, &self.base_value) {
+            (Some(m), _) => Some(m.clone()),
+            (None, Some(s)) => Some(Matcher::regex(s)),
+            (None, None) => None,
+        }
+    }
 }
@@ -10,8 +10,10 @@ pub fn mow(entry: &Entry, trait_: &str, thing: &str) -> Vec<Grain> {
             return vec![Grain {
                 base: entry.base.to_owned(),
                 base_value: entry.base_value.clone(),
+                base_matcher: entry.base_matcher.clone(),
                 leaf: trait_.to_owned(),
                 leaf_value: entry.base_value.clone(),
+                leaf_matcher: entry.base_matcher.clone(),
             }];
         }
 
@@ -25,8 +27,10 @@ pub fn mow(entry: &Entry, trait_: &str, thing: &str) -> Vec<Grain> {
             .map(|item| Grain {
                 base: entry.base.to_owned(),
                 base_value: entry.base_value.clone(),
+                base_matcher: entry.base_matcher.clone(),
                 leaf: trait_.to_owned(),
                 leaf_value: item.base_value.clone(),
+                leaf_matcher: item.base_matcher.clone(),
             })
             .collect();
 
@@ -38,16 +42,20 @@ pub fn mow(entry: &Entry, trait_: &str, thing: &str) -> Vec<Grain> {
             None => vec![Grain {
                 base: trait_.to_owned(),
                 base_value: entry.base_value.clone(),
+                base_matcher: entry.base_matcher.clone(),
                 leaf: thing.to_owned(),
                 leaf_value: None,
+                leaf_matcher: None,
             }],
             Some(items) => items
                 .iter()
                 .map(|item| Grain {
                     base: entry.base.to_owned(),
                     base_value: entry.base_value.clone(),
+                    base_matcher: entry.base_matcher.clone(),
                     leaf: thing.to_owned(),
                     leaf_value: item.base_value.clone(),
+                    leaf_matcher: item.base_matcher.clone(),
                 })
                 .collect(),
         };
@@ -70,8 +78,10 @@ pub fn mow(entry: &Entry, trait_: &str, thing: &str) -> Vec<Grain> {
                         .map(|branch_item| Grain {
                             base: trait_.to_owned(),
                             base_value: trunk_item.base_value.clone(),
+                            base_matcher: trunk_item.base_matcher.clone(),
                             leaf: thing.to_owned(),
                             leaf_value: branch_item.base_value.clone(),
+                            leaf_matcher: branch_item.base_matcher.clone(),
                         })
                         .collect();
 
@@ -82,8 +92,10 @@ pub fn mow(entry: &Entry, trait_: &str, thing: &str) -> Vec<Grain> {
                     let grain = Grain {
                         base: trait_.to_owned(),
                         base_value: trunk_item.base_value.clone(),
+                        base_matcher: trunk_item.base_matcher.clone(),
                         leaf: thing.to_owned(),
                         leaf_value: None,
+                        leaf_matcher: None,
                     };
 
                     [&with_trunk_item[..], &[grain]].concat()
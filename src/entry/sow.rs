@@ -17,12 +17,14 @@ pub fn sow(entry: &Entry, grain: &Grain, trait_: &str, thing: &str) -> Entry {
                 None => Entry {
                     base: thing.to_owned(),
                     base_value: Some(grain_leaf_value.to_owned()),
+                    base_matcher: None,
                     leader_value: None,
                     leaves: entry.leaves.clone(),
                 },
                 Some(e) => Entry {
                     base: thing.to_owned(),
                     base_value: Some(grain_leaf_value.to_owned()),
+                    base_matcher: None,
                     leader_value: None,
                     leaves: entry.leaves.clone(),
                 },
@@ -36,6 +38,7 @@ pub fn sow(entry: &Entry, grain: &Grain, trait_: &str, thing: &str) -> Entry {
         let thing_item = Entry {
             base: thing.to_owned(),
             base_value: grain.leaf_value.clone(),
+            base_matcher: grain.leaf_matcher.clone(),
             leader_value: None,
             leaves: HashMap::new(),
         };
@@ -53,6 +56,7 @@ pub fn sow(entry: &Entry, grain: &Grain, trait_: &str, thing: &str) -> Entry {
         return Entry {
             base: entry.base.to_owned(),
             base_value: entry.base_value.clone(),
+            base_matcher: entry.base_matcher.clone(),
             leader_value: None,
             leaves,
         };
@@ -76,6 +80,7 @@ pub fn sow(entry: &Entry, grain: &Grain, trait_: &str, thing: &str) -> Entry {
                 let thing_item = Entry {
                     base: grain.leaf.to_owned(),
                     base_value: grain.leaf_value.clone(),
+                    base_matcher: grain.leaf_matcher.clone(),
                     leader_value: None,
                     leaves: HashMap::new(),
                 };
@@ -96,6 +101,7 @@ pub fn sow(entry: &Entry, grain: &Grain, trait_: &str, thing: &str) -> Entry {
                     Entry {
                         base: trunk_item.base.to_owned(),
                         base_value: trunk_item.base_value.clone(),
+                        base_matcher: trunk_item.base_matcher.clone(),
                         leader_value: None,
                         leaves,
                     }
@@ -129,6 +135,7 @@ pub fn sow(entry: &Entry, grain: &Grain, trait_: &str, thing: &str) -> Entry {
     let foo = Entry {
         base: entry.base.to_owned(),
         base_value: entry.base_value.clone(),
+        base_matcher: entry.base_matcher.clone(),
         leader_value: None,
         leaves: leaves_new,
     };
@@ -1,110 +1,187 @@
 use super::Entry;
-use crate::{Error, Result};
-use serde_json::Value;
+use crate::matcher::{is_operator_object, matcher_from_object};
+use crate::{Error, Matcher, Result};
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 
-impl TryFrom<Value> for Entry {
-    type Error = Error;
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
 
-    fn try_from(value: Value) -> Result<Self> {
-        // validate that value is object
-        match value {
-            Value::Null => Err(Error::from_message("")),
-            Value::Bool(_) => Err(Error::from_message("")),
-            Value::Number(_) => Err(Error::from_message("")),
-            Value::String(_) => Err(Error::from_message("")),
-            Value::Array(_) => Err(Error::from_message("")),
-            Value::Object(v) => {
-                let base = match &v["_"] {
-                    Value::Null => return Err(Error::from_message("")),
-                    Value::Bool(_) => return Err(Error::from_message("")),
-                    Value::Number(_) => return Err(Error::from_message("")),
-                    Value::String(s) => s,
-                    Value::Array(_) => return Err(Error::from_message("")),
-                    Value::Object(_) => return Err(Error::from_message("")),
-                };
+fn string_from_value(value: &Value, path: &str) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.to_owned()),
+        v => Err(Error::invalid_query(
+            path,
+            format!("expected string, found {}", type_name(v)),
+        )),
+    }
+}
+
+// numbers and booleans of typed json are stored as they are written,
+// a write checks them against the type of their branch like any other value
+fn scalar_from_value(value: &Value, path: &str) -> Result<String> {
+    match value {
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        v => string_from_value(v, path),
+    }
+}
+
+// string, number or boolean, or operators that stand in its place
+fn query_value_from_value(value: &Value, path: &str) -> Result<(Option<String>, Option<Matcher>)> {
+    match value {
+        Value::Object(o) if is_operator_object(o) => {
+            Ok((None, Some(matcher_from_object(o, path)?)))
+        }
+        v => Ok((Some(scalar_from_value(v, path)?), None)),
+    }
+}
+
+fn leaf_from_value(key: &str, value: &Value, path: &str) -> Result<Entry> {
+    match value {
+        Value::Object(o) if !is_operator_object(o) => entry_from_value(value, path),
+        Value::String(_) | Value::Number(_) | Value::Bool(_) | Value::Object(_) => {
+            let (base_value, base_matcher) = query_value_from_value(value, path)?;
+
+            Ok(Entry {
+                base: key.to_owned(),
+                base_value,
+                base_matcher,
+                leader_value: None,
+                leaves: HashMap::new(),
+            })
+        }
+        v => Err(Error::invalid_query(
+            path,
+            format!("expected string, number, boolean or object, found {}", type_name(v)),
+        )),
+    }
+}
+
+// group of an $or query, with the base of the query unless it names one
+fn group_from_value(base: &str, value: &Value, path: &str) -> Result<Entry> {
+    let mut group = match value {
+        Value::Object(o) => o.clone(),
+        v => {
+            return Err(Error::invalid_query(
+                path,
+                format!("expected object, found {}", type_name(v)),
+            ))
+        }
+    };
+
+    group.entry("_").or_insert_with(|| base.into());
+
+    let entry = entry_from_object(&group, path)?;
+
+    if entry.base != base {
+        return Err(Error::invalid_query(
+            format!("{}._", path),
+            format!("expected base {}", base),
+        ));
+    }
+
+    Ok(entry)
+}
+
+fn entry_from_object(v: &Map<String, Value>, path: &str) -> Result<Entry> {
+    let base = match v.get("_") {
+        None => return Err(Error::invalid_query(format!("{}._", path), "missing base")),
+        Some(b) => string_from_value(b, &format!("{}._", path))?,
+    };
+
+    let (base_value, base_matcher) = match v.get(&base) {
+        None => (None, None),
+        Some(b) => query_value_from_value(b, &format!("{}.{}", path, base))?,
+    };
+
+    let leader_value = match v.get("__") {
+        None => None,
+        Some(l) => Some(string_from_value(l, &format!("{}.__", path))?),
+    };
+
+    let leaves = v
+        .iter()
+        .filter(|(key, _)| (*key != "_") && (**key != base) && (*key != "__"))
+        .map(|(key, val)| {
+            let leaf_path = format!("{}.{}", path, key);
 
-                let base_value = if v.contains_key(base) {
-                    match &v[base] {
-                        Value::Null => return Err(Error::from_message("")),
-                        Value::Bool(_) => return Err(Error::from_message("")),
-                        Value::Number(_) => return Err(Error::from_message("")),
-                        Value::String(s) => Some(s),
-                        Value::Array(_) => return Err(Error::from_message("")),
-                        Value::Object(_) => return Err(Error::from_message("")),
+            if key == "$or" {
+                let groups = match val {
+                    Value::Array(vs) => vs
+                        .iter()
+                        .enumerate()
+                        .map(|(i, v)| group_from_value(&base, v, &format!("{}[{}]", leaf_path, i)))
+                        .collect::<Result<Vec<Entry>>>()?,
+                    v => {
+                        return Err(Error::invalid_query(
+                            leaf_path,
+                            format!("expected array, found {}", type_name(v)),
+                        ))
                     }
-                } else {
-                    None
                 };
 
-                let leader_value = if v.contains_key("__") {
-                    match &v["__"] {
-                        Value::Null => return Err(Error::from_message("")),
-                        Value::Bool(_) => return Err(Error::from_message("")),
-                        Value::Number(_) => return Err(Error::from_message("")),
-                        Value::String(s) => Some(s),
-                        Value::Array(_) => return Err(Error::from_message("")),
-                        Value::Object(_) => return Err(Error::from_message("")),
-                    }
-                } else {
-                    None
+                return Ok((key.to_owned(), groups));
+            }
+
+            if key == "$text" {
+                let text = Entry {
+                    base: key.to_owned(),
+                    base_value: Some(string_from_value(val, &leaf_path)?),
+                    base_matcher: None,
+                    leader_value: None,
+                    leaves: HashMap::new(),
                 };
 
-                let leaves = v
-                    .iter()
-                    .filter(|(key, _)| (*key != "_") && (*key != base) && (*key != "__"))
-                    .map(|(key, val)| {
-                        let values: Vec<Entry> = match val {
-                            Value::Null => return Err(Error::from_message("")),
-                            Value::Bool(_) => return Err(Error::from_message("")),
-                            Value::Number(_) => return Err(Error::from_message("")),
-                            Value::String(s) => {
-                                vec![Entry {
-                                    base: key.to_owned(),
-                                    base_value: Some(s.to_owned()),
-                                    leader_value: None,
-                                    leaves: HashMap::new(),
-                                }]
-                            }
-                            Value::Array(vs) => vs
-                                .iter()
-                                .map(|v| match v {
-                                    Value::Null => return Err(Error::from_message("")),
-                                    Value::Bool(_) => return Err(Error::from_message("")),
-                                    Value::Number(_) => return Err(Error::from_message("")),
-                                    Value::String(s) => Ok(Entry {
-                                        base: key.to_owned(),
-                                        base_value: Some(s.to_owned()),
-                                        leader_value: None,
-                                        leaves: HashMap::new(),
-                                    }),
-                                    Value::Array(_) => return Err(Error::from_message("")),
-                                    Value::Object(_) => {
-                                        let e: Entry = v.clone().try_into()?;
-
-                                        Ok(e)
-                                    }
-                                })
-                                .collect::<Result<Vec<Entry>>>()?,
-                            Value::Object(_) => {
-                                let e: Entry = val.clone().try_into()?;
-
-                                vec![e]
-                            }
-                        };
-
-                        Ok((key.to_owned(), values))
-                    })
-                    .collect::<Result<HashMap<String, Vec<Entry>>>>()?;
-
-                Ok(Entry {
-                    base: base.to_owned(),
-                    base_value: base_value.cloned(),
-                    leader_value: leader_value.cloned(),
-                    leaves,
-                })
+                return Ok((key.to_owned(), vec![text]));
             }
-        }
+
+            let values: Vec<Entry> = match val {
+                Value::Array(vs) => vs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| leaf_from_value(key, v, &format!("{}[{}]", leaf_path, i)))
+                    .collect::<Result<Vec<Entry>>>()?,
+                v => vec![leaf_from_value(key, v, &leaf_path)?],
+            };
+
+            Ok((key.to_owned(), values))
+        })
+        .collect::<Result<HashMap<String, Vec<Entry>>>>()?;
+
+    Ok(Entry {
+        base,
+        base_value,
+        base_matcher,
+        leader_value,
+        leaves,
+    })
+}
+
+fn entry_from_value(value: &Value, path: &str) -> Result<Entry> {
+    // validate that value is object
+    match value {
+        Value::Object(v) => entry_from_object(v, path),
+        v => Err(Error::invalid_query(
+            path,
+            format!("expected object, found {}", type_name(v)),
+        )),
+    }
+}
+
+impl TryFrom<Value> for Entry {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self> {
+        entry_from_value(&value, "$")
     }
 }
 
@@ -1,21 +1,57 @@
 use std::io::Write;
+use std::path::Path;
 use std::{fmt, io};
 
 use backtrace::Backtrace;
 use crossterm::style::{Attribute, Color, ResetColor, SetAttribute, SetForegroundColor};
 use serde::{Serialize, Serializer};
 
+use crate::schema::{SchemaProblem, ValueType};
+use crate::version::VersionError;
+
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// new kinds are added as the crate grows, so matches need a wildcard arm
 #[derive(Debug)]
-pub struct Error {
-    inner: Box<dyn std::error::Error + Send + Sync>,
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// query is not valid query object notation, path points to the bad key
+    InvalidQuery { path: String, message: String },
+    /// branch is not in the schema
+    UnknownBranch(String),
+    /// operation needs a value of the base
+    MissingBaseValue(String),
+    /// value is not of the type declared for its branch
+    InvalidValue {
+        branch: String,
+        value: String,
+        value_type: ValueType,
+    },
+    /// tablet line can't be read, line numbers start at 1
+    MalformedLine {
+        file: String,
+        line: u64,
+        message: String,
+    },
+    /// schema branches that lead back to themselves, with every problem of the schema
+    SchemaCycle {
+        branches: Vec<String>,
+        problems: Vec<SchemaProblem>,
+    },
+    /// schema problems, the first of them not a cycle
+    InvalidSchema(Vec<SchemaProblem>),
+    Version(VersionError),
+    Io(io::Error),
+    Csv(csv::Error),
+    Json(serde_json::Error),
+    Regex(regex::Error),
+    Message(String),
 }
 
 #[derive(Debug)]
-struct Context {
-    message: String,
-    error: Error,
+pub struct Error {
+    kind: ErrorKind,
+    context: Vec<String>,
 }
 
 impl Error {
@@ -38,75 +74,218 @@ impl Error {
         Ok(())
     }
 
-    pub fn from_message(message: impl ToString) -> Self {
+    pub fn new(kind: ErrorKind) -> Self {
         Error {
-            inner: message.to_string().into(),
+            kind,
+            context: vec![],
         }
     }
 
-    pub fn with_context(error: impl Into<Self>, message: impl ToString) -> Self {
-        Self::from(Context {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn from_message(message: impl ToString) -> Self {
+        Error::new(ErrorKind::Message(message.to_string()))
+    }
+
+    pub fn invalid_query(path: impl ToString, message: impl ToString) -> Self {
+        Error::new(ErrorKind::InvalidQuery {
+            path: path.to_string(),
             message: message.to_string(),
-            error: error.into(),
         })
     }
 
+    pub fn unknown_branch(branch: impl ToString) -> Self {
+        Error::new(ErrorKind::UnknownBranch(branch.to_string()))
+    }
+
+    pub fn missing_base_value(base: impl ToString) -> Self {
+        Error::new(ErrorKind::MissingBaseValue(base.to_string()))
+    }
+
+    pub fn invalid_value(branch: impl ToString, value: impl ToString, value_type: ValueType) -> Self {
+        Error::new(ErrorKind::InvalidValue {
+            branch: branch.to_string(),
+            value: value.to_string(),
+            value_type,
+        })
+    }
+
+    pub fn malformed_line(file: &Path, line: u64, message: impl ToString) -> Self {
+        Error::new(ErrorKind::MalformedLine {
+            file: file.display().to_string(),
+            line,
+            message: message.to_string(),
+        })
+    }
+
+    // validate lists cycles first since they break schema traversal,
+    // so the kind follows the first problem and keeps the rest
+    pub fn invalid_schema(problems: Vec<SchemaProblem>) -> Self {
+        let cycle = match problems.first() {
+            Some(SchemaProblem::Cycle(branches)) => Some(branches.clone()),
+            Some(SchemaProblem::SelfLoop(branch)) => Some(vec![branch.clone(), branch.clone()]),
+            _ => None,
+        };
+
+        match cycle {
+            Some(branches) => Error::new(ErrorKind::SchemaCycle { branches, problems }),
+            None => Error::new(ErrorKind::InvalidSchema(problems)),
+        }
+    }
+
+    // read errors of the csv reader into a line error of the tablet
+    pub fn from_csv(file: &Path, err: csv::Error) -> Self {
+        match err.position() {
+            None => err.into(),
+            Some(p) => Error::malformed_line(file, p.line(), err),
+        }
+    }
+
+    pub fn with_context(error: impl Into<Self>, message: impl ToString) -> Self {
+        let mut error = error.into();
+
+        error.context.insert(0, message.to_string());
+
+        error
+    }
+
     pub fn context(self, message: impl ToString) -> Self {
         Error::with_context(self, message)
     }
+
+    pub fn downcast_ref<T: std::error::Error + 'static>(&self) -> Option<&T> {
+        let err: &(dyn std::error::Error + 'static) = match &self.kind {
+            ErrorKind::Version(e) => e,
+            ErrorKind::Io(e) => e,
+            ErrorKind::Csv(e) => e,
+            ErrorKind::Json(e) => e,
+            ErrorKind::Regex(e) => e,
+            _ => return None,
+        };
+
+        err.downcast_ref::<T>()
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Error {
+        Error::new(kind)
+    }
 }
 
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Error {
-        Error { inner: err.into() }
+        Error::new(ErrorKind::Io(err))
     }
 }
 
 impl From<fmt::Error> for Error {
     fn from(err: fmt::Error) -> Error {
-        Error { inner: err.into() }
-    }
-}
-
-impl From<Context> for Error {
-    fn from(ctx: Context) -> Error {
-        Error { inner: ctx.into() }
+        Error::from_message(err)
     }
 }
 
 impl From<serde_json::Error> for Error {
     fn from(ctx: serde_json::Error) -> Error {
-        Error { inner: ctx.into() }
+        Error::new(ErrorKind::Json(ctx))
     }
 }
 
 impl From<csv::Error> for Error {
     fn from(ctx: csv::Error) -> Error {
-        Error { inner: ctx.into() }
+        Error::new(ErrorKind::Csv(ctx))
     }
 }
 
 impl From<regex::Error> for Error {
     fn from(ctx: regex::Error) -> Error {
-        Error { inner: ctx.into() }
+        Error::new(ErrorKind::Regex(ctx))
     }
 }
 
 impl From<dir_diff::Error> for Error {
     fn from(ctx: dir_diff::Error) -> Error {
-        Error { inner: ctx.into() }
+        match ctx {
+            dir_diff::Error::Io(e) => e.into(),
+            dir_diff::Error::StripPrefix(e) => Error::from_message(e),
+            dir_diff::Error::WalkDir(e) => Error::from_message(e),
+        }
+    }
+}
+
+impl From<VersionError> for Error {
+    fn from(ctx: VersionError) -> Error {
+        Error::new(ErrorKind::Version(ctx))
+    }
+}
+
+fn join_problems(problems: &[SchemaProblem]) -> String {
+    problems
+        .iter()
+        .map(|problem| problem.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::InvalidQuery { path, message } => {
+                write!(f, "invalid query at {}: {}", path, message)
+            }
+            ErrorKind::UnknownBranch(branch) => write!(f, "unknown branch \"{}\"", branch),
+            ErrorKind::MissingBaseValue(base) => write!(f, "missing value of base \"{}\"", base),
+            ErrorKind::InvalidValue {
+                branch,
+                value,
+                value_type,
+            } => write!(f, "value \"{}\" of \"{}\" is not of type {}", value, branch, value_type),
+            ErrorKind::MalformedLine {
+                file,
+                line,
+                message,
+            } => write!(f, "malformed line {} in {}: {}", line, file, message),
+            ErrorKind::SchemaCycle { branches, problems } => write!(
+                f,
+                "schema cycle {}, invalid schema: {}",
+                branches.join(" -> "),
+                join_problems(problems)
+            ),
+            ErrorKind::InvalidSchema(problems) => {
+                write!(f, "invalid schema: {}", join_problems(problems))
+            }
+            ErrorKind::Version(e) => e.fmt(f),
+            ErrorKind::Io(e) => e.fmt(f),
+            ErrorKind::Csv(e) => e.fmt(f),
+            ErrorKind::Json(e) => e.fmt(f),
+            ErrorKind::Regex(e) => e.fmt(f),
+            ErrorKind::Message(message) => message.fmt(f),
+        }
     }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.inner.fmt(f)
+        for message in self.context.iter() {
+            write!(f, "{}: ", message)?;
+        }
+
+        self.kind.fmt(f)
     }
 }
 
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        self.inner.source()
+        // wrapped errors are already displayed with the kind
+        match &self.kind {
+            ErrorKind::Io(e) => e.source(),
+            ErrorKind::Csv(e) => e.source(),
+            ErrorKind::Json(e) => e.source(),
+            ErrorKind::Regex(e) => e.source(),
+            _ => None,
+        }
     }
 }
 
@@ -132,18 +311,6 @@ impl Serialize for Error {
     }
 }
 
-impl fmt::Display for Context {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.message.fmt(f)
-    }
-}
-
-impl std::error::Error for Context {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        Some(&self.error)
-    }
-}
-
 impl serde::de::Error for Error {
     fn custom<T>(msg: T) -> Self
     where
@@ -1,11 +1,18 @@
 mod into_value;
 mod try_from;
+use crate::Matcher;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Grain {
     pub base: String,
     pub base_value: Option<String>,
+    /// operators of a query in place of the base value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_matcher: Option<Matcher>,
     pub leaf: String,
     pub leaf_value: Option<String>,
+    /// operators of a query in place of the leaf value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leaf_matcher: Option<Matcher>,
 }
@@ -3,75 +3,63 @@ use crate::{Error, Result};
 use serde_json::Value;
 use std::convert::TryFrom;
 
+fn string_from_value(value: &Value, path: &str) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.to_owned()),
+        _ => Err(Error::invalid_query(path, "expected string")),
+    }
+}
+
 impl TryFrom<Value> for Grain {
     type Error = Error;
 
     fn try_from(value: Value) -> Result<Self> {
-        match value {
-            Value::Null => Err(Error::from_message("")),
-            Value::Bool(_) => Err(Error::from_message("")),
-            Value::Number(_) => Err(Error::from_message("")),
-            Value::String(_) => Err(Error::from_message("")),
-            Value::Array(_) => Err(Error::from_message("")),
-            Value::Object(v) => {
-                let base = match &v["_"] {
-                    Value::Null => return Err(Error::from_message("")),
-                    Value::Bool(_) => return Err(Error::from_message("")),
-                    Value::Number(_) => return Err(Error::from_message("")),
-                    Value::String(s) => s,
-                    Value::Array(_) => return Err(Error::from_message("")),
-                    Value::Object(_) => return Err(Error::from_message("")),
-                };
-
-                let base_value = if v.contains_key(base) {
-                    match &v[base] {
-                        Value::Null => return Err(Error::from_message("")),
-                        Value::Bool(_) => return Err(Error::from_message("")),
-                        Value::Number(_) => return Err(Error::from_message("")),
-                        Value::String(s) => Some(s),
-                        Value::Array(_) => return Err(Error::from_message("")),
-                        Value::Object(_) => return Err(Error::from_message("")),
-                    }
-                } else {
-                    None
-                };
+        let v = match value {
+            Value::Object(v) => v,
+            _ => return Err(Error::invalid_query("$", "expected object")),
+        };
 
-                let leaf: Option<(String, Value)> = v
-                    .iter()
-                    .filter(|(key, _)| (*key != "_") && (*key != base))
-                    .try_fold(None, |with_pair, ((key, val))| {
-                        if with_pair.is_some() {
-                            Err(Error::from_message("more than one key in grain"))
-                        } else {
-                            Ok(Some((key.to_owned(), val.to_owned())))
-                        }
-                    })?;
+        let base = match v.get("_") {
+            None => return Err(Error::invalid_query("$._", "missing base")),
+            Some(b) => string_from_value(b, "$._")?,
+        };
 
-                match leaf {
-                    None => Ok(Grain {
-                        base: base.to_owned(),
-                        base_value: base_value.cloned(),
-                        leaf: "".to_owned(),
-                        leaf_value: None,
-                    }),
-                    Some((key, val)) => {
-                        let leaf_value: Option<String> = match val {
-                            Value::Null => return Err(Error::from_message("")),
-                            Value::Bool(_) => return Err(Error::from_message("")),
-                            Value::Number(_) => return Err(Error::from_message("")),
-                            Value::String(s) => Some(s.to_owned()),
-                            Value::Array(_) => return Err(Error::from_message("")),
-                            Value::Object(_) => return Err(Error::from_message("")),
-                        };
+        let base_value = match v.get(&base) {
+            None => None,
+            Some(b) => Some(string_from_value(b, &format!("$.{}", base))?),
+        };
 
-                        Ok(Grain {
-                            base: base.to_owned(),
-                            base_value: base_value.cloned(),
-                            leaf: key,
-                            leaf_value,
-                        })
-                    }
+        let leaf: Option<(String, Value)> = v
+            .iter()
+            .filter(|(key, _)| (*key != "_") && (**key != base))
+            .try_fold(None, |with_pair, (key, val)| {
+                if with_pair.is_some() {
+                    Err(Error::invalid_query(format!("$.{}", key), "more than one key in grain"))
+                } else {
+                    Ok(Some((key.to_owned(), val.to_owned())))
                 }
+            })?;
+
+        match leaf {
+            None => Ok(Grain {
+                base,
+                base_value,
+                base_matcher: None,
+                leaf: "".to_owned(),
+                leaf_value: None,
+                leaf_matcher: None,
+            }),
+            Some((key, val)) => {
+                let leaf_value = Some(string_from_value(&val, &format!("$.{}", key))?);
+
+                Ok(Grain {
+                    base,
+                    base_value,
+                    base_matcher: None,
+                    leaf: key,
+                    leaf_value,
+                    leaf_matcher: None,
+                })
             }
         }
     }
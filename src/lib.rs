@@ -5,11 +5,19 @@ pub mod error;
 mod grain;
 mod into_value;
 mod line;
+mod matcher;
 mod schema;
+mod version;
 
-pub use dataset::Dataset;
+pub use dataset::{
+    Aggregation, Bucket, Cascade, Changeset, Cursor, Dataset, SelectOptions, Sort, SortOrder,
+    TabletChange, TabletProblem, ValueOption,
+};
 pub use entry::Entry;
-pub use error::{Error, Result};
+pub use error::{Error, ErrorKind, Result};
 pub use grain::Grain;
 pub use into_value::IntoValue;
-pub use schema::{Branch, Leaves, Schema, Trunks};
+pub use line::Line;
+pub use matcher::{Matcher, Operator};
+pub use schema::{Branch, Leaves, Schema, SchemaProblem, Trunks, ValueType};
+pub use version::{Version, VersionError};
@@ -1,6 +1,8 @@
 #![allow(warnings)]
 use clap::{Parser, Subcommand};
-use csvs::{Entry, Error, Result, Dataset};
+use csvs::{Aggregation, Cascade, Changeset, Cursor, Entry, Error, IntoValue, Result, Dataset, SelectOptions, Sort, ValueOption, ValueType};
+use futures_util::pin_mut;
+use futures_util::stream::StreamExt;
 use serde_json::{from_str, Value};
 mod test;
 use std::env;
@@ -24,77 +26,309 @@ enum Commands {
         /// A json string in query object notation
         #[arg(short, long)]
         query: String,
+
+        /// Branch to order entries by, like actdate or actdate:desc
+        #[arg(long)]
+        sort: Option<String>,
+
+        /// Print at most this many entries
+        #[arg(long)]
+        limit: Option<usize>,
+
+        /// Skip this many entries
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+    },
+    /// Print the number of entries that match query
+    Count {
+        /// A json string in query object notation
+        #[arg(short, long)]
+        query: String,
+    },
+    /// Print the number of entries that match query for each value of a branch
+    Stats {
+        /// A json string in query object notation
+        #[arg(short, long)]
+        query: String,
+
+        /// Branch to group entries by, like actname or actdate:year, actdate:month, actdate:day
+        #[arg(short, long)]
+        branch: String,
+    },
+    /// Print the distinct values of a branch
+    Options {
+        /// Branch of the values
+        branch: String,
+
+        /// Print only values that start with this
+        #[arg(long)]
+        prefix: Option<String>,
+
+        /// Also print how many trunk values have each value, or leaf values for a root
+        #[arg(long)]
+        count: bool,
     },
     /// Delete entries that match query
     Delete {
         /// A json string in query object notation
         #[arg(short, long)]
         query: String,
+
+        /// Also delete descendant values that no other entry references
+        #[arg(long)]
+        cascade: bool,
+
+        /// Print the tablet edits as json instead of writing them
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Update an entry from query
     Update {
         /// A json string in query object notation
         #[arg(short, long)]
         query: String,
+
+        /// Print the tablet edits as json instead of writing them
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Add an entry from query
     Insert {
         /// A json string in query object notation
         #[arg(short, long)]
         query: String,
+
+        /// Print the tablet edits as json instead of writing them
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Create a new dataset
     Create {
         /// Name of the dataset directory
         #[arg(short, long)]
         name: String,
+
+        /// A json string with the schema entry
+        #[arg(short, long, conflicts_with = "schema_file")]
+        schema: Option<String>,
+
+        /// Path to a json file with the schema entry
+        #[arg(long)]
+        schema_file: Option<String>,
+    },
+    /// Rewrite a legacy dataset into the current layout
+    Migrate,
+    /// Build reverse indexes for lookups by leaf value
+    Index {
+        /// Tablet filename like datum-actdate.csv, all tablets if none given
+        #[arg(short, long)]
+        tablet: Vec<String>,
+
+        /// Build text indexes of leaf words for $text queries instead
+        #[arg(long)]
+        text: bool,
+    },
+    /// Declare the type of a branch's values
+    Type {
+        /// Branch of the values
+        #[arg(short, long)]
+        branch: String,
+
+        /// One of date, datetime, integer, decimal, boolean, string, removes the declaration if none given
+        #[arg(short = 't', long = "type")]
+        value_type: Option<String>,
+    },
+    /// Find problems in dataset tablets
+    Check {
+        /// Repair the problems that were found
+        #[arg(long)]
+        fix: bool,
     },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
     let cli = Cli::parse();
 
+    if let Err(e) = run(cli).await {
+        let mut stdout = std::io::stdout().lock();
+
+        // fall back to debug output if the terminal can't be styled
+        if e.write(&mut stdout).is_err() {
+            eprintln!("{:?}", e);
+        }
+
+        println!();
+
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
     let path = match cli.path {
         Some(p) => std::path::Path::new(&p).to_owned(),
         None => env::current_dir()?,
     };
 
-    let dataset = Dataset::new(&path);
-
     // println!("Hello {}!", path.display());
 
+    // a new dataset is created inside path which need not be a dataset
+    // a legacy dataset can't be opened before migration
+    let dataset = match cli.command {
+        Some(Commands::Create { .. }) | Some(Commands::Migrate) => Dataset::new(&path),
+        _ => Dataset::open(&path).await?,
+    };
+
     match &cli.command {
-        Some(Commands::Select { query }) => {
+        Some(Commands::Select {
+            query,
+            sort,
+            limit,
+            offset,
+        }) => {
             let query_json: Value = from_str(query)?;
 
             let query_record: Entry = query_json.try_into()?;
 
-            dataset.print_record(vec![query_record]).await?
+            let options = SelectOptions {
+                sort: sort.as_ref().map(|s| s.parse::<Sort>()).transpose()?,
+                limit: *limit,
+                offset: *offset,
+                after: None,
+            };
+
+            dataset.print_record(vec![query_record], options).await?
+        }
+        Some(Commands::Count { query }) => {
+            let query_json: Value = from_str(query)?;
+
+            let query_record: Entry = query_json.try_into()?;
+
+            println!("{}", dataset.count(vec![query_record]).await?);
+        }
+        Some(Commands::Stats { query, branch }) => {
+            let query_json: Value = from_str(query)?;
+
+            let query_record: Entry = query_json.try_into()?;
+
+            let aggregation: Aggregation = branch.parse()?;
+
+            // rows keep value before count, as options do
+            for (value, count) in dataset.aggregate(vec![query_record], aggregation).await? {
+                println!("{}", serde_json::to_string(&ValueOption { value, count })?);
+            }
+        }
+        Some(Commands::Options {
+            branch,
+            prefix,
+            count,
+        }) => {
+            let s = dataset.select_options(branch, prefix.as_deref());
+
+            pin_mut!(s); // needed for iteration
+
+            while let Some(option) = s.next().await {
+                let option = option?;
+
+                if *count {
+                    println!("{}", serde_json::to_string(&option)?);
+                } else {
+                    println!("{}", option.value);
+                }
+            }
         }
-        Some(Commands::Delete { query }) => {
+        Some(Commands::Delete {
+            query,
+            cascade,
+            dry_run,
+        }) => {
             let query_json: Value = from_str(query)?;
 
             let query_record: Entry = query_json.try_into()?;
 
-            dataset.delete_record(vec![query_record]).await;
+            let cascade = if *cascade {
+                Cascade::Exclusive
+            } else {
+                Cascade::None
+            };
+
+            if *dry_run {
+                let changeset = dataset.delete_record_dry(vec![query_record], cascade).await?;
+
+                println!("{}", serde_json::to_string_pretty(&changeset)?);
+            } else {
+                dataset.delete_record(vec![query_record], cascade).await?;
+            }
         }
-        Some(Commands::Update { query }) => {
+        Some(Commands::Update { query, dry_run }) => {
             let query_json: Value = from_str(query)?;
 
             let query_record: Entry = query_json.try_into()?;
 
-            dataset.update_record(vec![query_record]).await;
+            if *dry_run {
+                let changeset = dataset.update_record_dry(vec![query_record]).await?;
+
+                println!("{}", serde_json::to_string_pretty(&changeset)?);
+            } else {
+                dataset.update_record(vec![query_record]).await?;
+            }
         }
-        Some(Commands::Insert { query }) => {
+        Some(Commands::Insert { query, dry_run }) => {
             let query_json: Value = from_str(query)?;
 
             let query_record: Entry = query_json.try_into()?;
 
-            dataset.insert_record(vec![query_record]).await;
+            if *dry_run {
+                let changeset = dataset.insert_record_dry(vec![query_record]).await?;
+
+                println!("{}", serde_json::to_string_pretty(&changeset)?);
+            } else {
+                dataset.insert_record(vec![query_record]).await?;
+            }
         }
-        Some(Commands::Create { name }) => {
-            dataset.create(name);
+        Some(Commands::Create {
+            name,
+            schema,
+            schema_file,
+        }) => {
+            let schema_string = match (schema, schema_file) {
+                (Some(s), _) => Some(s.to_owned()),
+                (None, Some(f)) => Some(std::fs::read_to_string(f)?),
+                (None, None) => None,
+            };
+
+            let schema_record: Option<Entry> = match schema_string {
+                None => None,
+                Some(s) => Some(s.try_into()?),
+            };
+
+            dataset.create(name, schema_record).await?;
+        }
+        Some(Commands::Migrate) => {
+            Dataset::migrate(&path).await?;
+        }
+        Some(Commands::Index { tablet, text }) => {
+            if *text {
+                dataset.build_text_index(tablet.clone())?;
+            } else {
+                dataset.build_index(tablet.clone())?;
+            }
+        }
+        Some(Commands::Type { branch, value_type }) => {
+            let value_type = value_type.as_ref().map(|t| t.parse::<ValueType>()).transpose()?;
+
+            dataset.set_type(branch, value_type).await?;
+        }
+        Some(Commands::Check { fix }) => {
+            let s = dataset.check(*fix);
+
+            pin_mut!(s); // needed for iteration
+
+            while let Some(problem) = s.next().await {
+                let problem = problem?;
+
+                println!("{}", problem);
+            }
         }
         None => {
             // show help
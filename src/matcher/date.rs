@@ -0,0 +1,113 @@
+use crate::schema::{civil_from_days, days_from_civil, days_in_month, parse_date, parse_digits};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// days since 1970-01-01 of the current day in UTC
+pub fn today() -> i64 {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    (seconds / 86400) as i64
+}
+
+// first and last day of YYYY, YYYY-MM or YYYY-MM-DD
+fn partial_range(s: &str) -> Option<(i64, i64)> {
+    let mut parts = s.split('-');
+
+    let year = parse_digits(parts.next()?, 4)?;
+
+    let month = match parts.next() {
+        None => return Some((days_from_civil(year, 1, 1), days_from_civil(year, 12, 31))),
+        Some(m) => parse_digits(m, 2)?,
+    };
+
+    if !(1..=12).contains(&month) {
+        return None;
+    }
+
+    match parts.next() {
+        None => Some((
+            days_from_civil(year, month, 1),
+            days_from_civil(year, month, days_in_month(year, month)),
+        )),
+        Some(_) => parse_date(s).map(|day| (day, day)),
+    }
+}
+
+// same day of the month some months before, or the last day of a shorter month
+fn months_before(days: i64, months: i64) -> i64 {
+    let (year, month, day) = civil_from_days(days);
+
+    let index = year * 12 + month - 1 - months;
+
+    let (year, month) = (index.div_euclid(12), index.rem_euclid(12) + 1);
+
+    days_from_civil(year, month, day.min(days_in_month(year, month)))
+}
+
+// today, or the days up to today of last 30 days, last 2 weeks, last month
+fn relative_range(s: &str, today: i64) -> Option<(i64, i64)> {
+    if s == "today" {
+        return Some((today, today));
+    }
+
+    let rest = s.strip_prefix("last ")?;
+
+    let (count, unit) = rest.split_once(' ').unwrap_or(("1", rest));
+
+    if !count.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let count = i64::from(count.parse::<u32>().ok().filter(|n| *n > 0)?);
+
+    let start = match unit.strip_suffix('s').unwrap_or(unit) {
+        "day" => today - count,
+        "week" => today - 7 * count,
+        "month" => months_before(today, count),
+        "year" => months_before(today, 12 * count),
+        _ => return None,
+    };
+
+    Some((start + 1, today))
+}
+
+/// first and last day that a $date query covers, both included:
+/// a partial date like 2001 or 2001-05, a range of them like 2001-01..2001-06
+/// with either end left open, today, or last 30 days, weeks, months or years
+pub fn date_range(query: &str, today: i64) -> Option<(i64, i64)> {
+    let query = query.trim();
+
+    let (start, end) = match query.split_once("..") {
+        None => partial_range(query).or_else(|| relative_range(query, today))?,
+        Some(("", "")) => return None,
+        Some((from, to)) => {
+            let start = match from.trim() {
+                "" => i64::MIN,
+                f => partial_range(f)?.0,
+            };
+
+            let end = match to.trim() {
+                "" => i64::MAX,
+                t => partial_range(t)?.1,
+            };
+
+            (start, end)
+        }
+    };
+
+    Some((start, end)).filter(|(start, end)| start <= end)
+}
+
+/// first and last day of a date or datetime value as written,
+/// a partial date like 2001 or 2001-05 covers each of its days
+/// and a datetime counts on the day it was recorded in
+pub fn date_span(value: &str) -> Option<(i64, i64)> {
+    match value.split_at_checked(10) {
+        Some((date, rest)) if rest.starts_with('T') || rest.starts_with(' ') => {
+            parse_date(date).map(|day| (day, day))
+        }
+        _ => partial_range(value),
+    }
+}
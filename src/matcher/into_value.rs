@@ -0,0 +1,35 @@
+use super::{Matcher, Operator};
+use crate::IntoValue;
+use serde_json::{json, Value};
+
+impl IntoValue for Matcher {
+    fn into_value(self) -> Value {
+        let mut value: Value = json!({});
+
+        for operator in self.0 {
+            match operator {
+                Operator::Eq(s) => value["$eq"] = s.into(),
+                Operator::Regex(s) => value["$regex"] = s.into(),
+                Operator::Prefix(s) => value["$prefix"] = s.into(),
+                Operator::Gt(s) => value["$gt"] = s.into(),
+                Operator::Gte(s) => value["$gte"] = s.into(),
+                Operator::Lt(s) => value["$lt"] = s.into(),
+                Operator::Lte(s) => value["$lte"] = s.into(),
+                Operator::In(vs) => value["$in"] = vs.into(),
+                Operator::Not(m) => value["$not"] = m.into_value(),
+                Operator::Exists(b) => value["$exists"] = b.into(),
+                Operator::Fold(s) => value["$fold"] = s.into(),
+                Operator::Date(s) => value["$date"] = s.into(),
+                Operator::Fuzzy(s, distance) => {
+                    value["$fuzzy"] = s.into();
+
+                    if let Some(d) = distance {
+                        value["$distance"] = d.into();
+                    }
+                }
+            }
+        }
+
+        value
+    }
+}
@@ -0,0 +1,213 @@
+mod date;
+mod into_value;
+mod text;
+mod try_from;
+pub use date::{date_range, date_span, today};
+pub use text::{auto_distance, edit_distance, fold, normalize, tokenize};
+pub use try_from::{is_operator_object, matcher_from_object};
+use crate::ValueType;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// comparison of a tablet value with an operand, like {"$prefix": "2001-"}
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub enum Operator {
+    Eq(String),
+    Regex(String),
+    Prefix(String),
+    Gt(String),
+    Gte(String),
+    Lt(String),
+    Lte(String),
+    In(Vec<String>),
+    Not(Matcher),
+    Exists(bool),
+    /// equal after case folding and stripping diacritics
+    Fold(String),
+    /// folded values within the edit distance, or an automatic one
+    Fuzzy(String, Option<usize>),
+    /// values with a day in a partial date, a range or a relative one,
+    /// a partial value like 2001-05 has each of its days
+    Date(String),
+}
+
+/// operators that a value must all satisfy,
+/// a bare string in a query is one regex
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct Matcher(pub Vec<Operator>);
+
+// literal regex like ^value1$ matches exactly one value
+fn literal_regex(re_str: &str) -> Option<String> {
+    let inner = re_str.strip_prefix('^')?.strip_suffix('$')?;
+
+    let mut literal = String::new();
+
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(e) if e.is_ascii_punctuation() => literal.push(e),
+                // escape classes like \d, or an escaped $ at the end
+                _ => return None,
+            },
+            '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' => {
+                return None
+            }
+            _ => literal.push(c),
+        }
+    }
+
+    Some(literal)
+}
+
+impl Matcher {
+    /// matcher of a bare string in a query
+    pub fn regex(value: &str) -> Matcher {
+        Matcher(vec![Operator::Regex(value.to_owned())])
+    }
+
+    /// matcher of exactly one value
+    pub fn eq(value: &str) -> Matcher {
+        Matcher(vec![Operator::Eq(value.to_owned())])
+    }
+
+    /// decided on all values of a branch rather than on each value
+    pub fn is_negative(&self) -> bool {
+        self.0
+            .iter()
+            .any(|operator| matches!(operator, Operator::Not(_) | Operator::Exists(_)))
+    }
+
+    /// folded term and the edits allowed for it, if the matcher is fuzzy
+    pub fn fuzzy(&self) -> Option<(String, usize)> {
+        self.0.iter().find_map(|operator| match operator {
+            Operator::Fuzzy(term, distance) => {
+                let term = fold(term);
+
+                let distance = distance.unwrap_or_else(|| auto_distance(&term));
+
+                Some((term, distance))
+            }
+            _ => None,
+        })
+    }
+
+    /// the only value that can match, if there is one
+    pub fn literal(&self) -> Option<String> {
+        self.0.iter().find_map(|operator| match operator {
+            Operator::Eq(s) => Some(s.to_owned()),
+            Operator::In(vs) if vs.len() == 1 => Some(vs[0].to_owned()),
+            Operator::Regex(s) => literal_regex(s),
+            _ => None,
+        })
+    }
+
+    pub fn compile(&self) -> std::result::Result<Pattern, regex::Error> {
+        self.compile_as(ValueType::String)
+    }
+
+    /// pattern that compares values in the order of their type
+    pub fn compile_as(&self, value_type: ValueType) -> std::result::Result<Pattern, regex::Error> {
+        let tests = self
+            .0
+            .iter()
+            .map(|operator| match operator {
+                Operator::Regex(s) => Ok(Test::Regex(Regex::new(s)?)),
+                Operator::Not(m) => Ok(Test::Not(m.compile_as(value_type)?)),
+                Operator::Exists(b) => Ok(Test::Exists(*b)),
+                // fold the operand once rather than for every value
+                Operator::Fold(s) => Ok(Test::Operator(Operator::Fold(fold(s)), value_type)),
+                Operator::Fuzzy(term, distance) => {
+                    let term = fold(term);
+
+                    let distance = distance.unwrap_or_else(|| auto_distance(&term));
+
+                    Ok(Test::Operator(Operator::Fuzzy(term, Some(distance)), value_type))
+                }
+                // relative dates count from the day the pattern is compiled
+                Operator::Date(s) => {
+                    let (start, end) = date_range(s, today()).unwrap_or((1, 0));
+
+                    Ok(Test::Dates(start, end))
+                }
+                op => Ok(Test::Operator(op.clone(), value_type)),
+            })
+            .collect::<std::result::Result<Vec<Test>, regex::Error>>()?;
+
+        Ok(Pattern(tests))
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Test {
+    Regex(Regex),
+    Not(Pattern),
+    Exists(bool),
+    /// first and last day, both included
+    Dates(i64, i64),
+    Operator(Operator, ValueType),
+}
+
+impl Test {
+    fn is_match(&self, value: &str) -> bool {
+        match self {
+            Test::Regex(re) => re.is_match(value),
+            Test::Not(pattern) => !pattern.is_match(value),
+            Test::Exists(b) => *b,
+            // a partial value matches if any of its days is in the range
+            Test::Dates(start, end) => date_span(value).is_some_and(|(first, last)| *start <= last && first <= *end),
+            Test::Operator(operator, value_type) => match operator {
+                Operator::Eq(s) => value == s,
+                Operator::Prefix(s) => value.starts_with(s.as_str()),
+                Operator::Gt(s) => value_type.compare(value, s).is_gt(),
+                Operator::Gte(s) => value_type.compare(value, s).is_ge(),
+                Operator::Lt(s) => value_type.compare(value, s).is_lt(),
+                Operator::Lte(s) => value_type.compare(value, s).is_le(),
+                Operator::In(vs) => vs.iter().any(|v| v == value),
+                Operator::Fold(s) => fold(value) == *s,
+                Operator::Fuzzy(s, distance) => edit_distance(&fold(value), s) <= distance.unwrap_or(0),
+                // compiled into other tests
+                Operator::Regex(_) | Operator::Not(_) | Operator::Exists(_) | Operator::Date(_) => {
+                    unreachable!()
+                }
+            },
+        }
+    }
+
+    fn is_negative(&self) -> bool {
+        matches!(self, Test::Not(_) | Test::Exists(_))
+    }
+}
+
+/// matcher with its regexes compiled
+#[derive(Debug, Clone)]
+pub struct Pattern(Vec<Test>);
+
+impl Pattern {
+    pub fn is_match(&self, value: &str) -> bool {
+        self.0.iter().all(|test| test.is_match(value))
+    }
+
+    /// one of the values matches every operator but negations,
+    /// and none of the values match a negation
+    pub fn is_match_any(&self, values: &[String]) -> bool {
+        let is_match_negative = self.0.iter().all(|test| match test {
+            Test::Not(pattern) => !values.iter().any(|v| pattern.is_match(v)),
+            Test::Exists(b) => values.is_empty() != *b,
+            _ => true,
+        });
+
+        let is_positive = self.0.iter().any(|test| !test.is_negative());
+
+        let is_match_positive = !is_positive
+            || values.iter().any(|v| {
+                self.0
+                    .iter()
+                    .filter(|test| !test.is_negative())
+                    .all(|test| test.is_match(v))
+            });
+
+        is_match_negative && is_match_positive
+    }
+}
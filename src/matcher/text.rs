@@ -0,0 +1,74 @@
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// compatibility composed and lowercase, so that
+/// differently encoded and cased forms of a word compare equal
+pub fn normalize(s: &str) -> String {
+    s.nfkc().flat_map(char::to_lowercase).collect()
+}
+
+/// distinct normalized words of a value, in order
+pub fn tokenize(s: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = vec![];
+
+    for token in normalize(s).split(|c: char| !c.is_alphanumeric()) {
+        if !token.is_empty() && !tokens.iter().any(|t| t == token) {
+            tokens.push(token.to_owned());
+        }
+    }
+
+    tokens
+}
+
+/// compatibility decomposed without combining marks and lowercase,
+/// so that Émile, EMILE and emile compare equal
+pub fn fold(s: &str) -> String {
+    s.nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// optimal string alignment distance in chars,
+/// levenshtein that also counts a swap of adjacent chars as one edit
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+
+    let b: Vec<char> = b.chars().collect();
+
+    let mut row_before: Vec<usize> = vec![0; b.len() + 1];
+
+    let mut row_prev: Vec<usize> = (0..=b.len()).collect();
+
+    for i in 0..a.len() {
+        let mut row: Vec<usize> = vec![i + 1; b.len() + 1];
+
+        for j in 0..b.len() {
+            let substitution = row_prev[j] + usize::from(a[i] != b[j]);
+
+            let mut distance = substitution.min(row[j] + 1).min(row_prev[j + 1] + 1);
+
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                distance = distance.min(row_before[j - 1] + 1);
+            }
+
+            row[j + 1] = distance;
+        }
+
+        row_before = row_prev;
+
+        row_prev = row;
+    }
+
+    row_prev[b.len()]
+}
+
+/// edits allowed for a fuzzy term without a distance,
+/// none for short terms, then one, then two from six chars
+pub fn auto_distance(term: &str) -> usize {
+    match term.chars().count() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
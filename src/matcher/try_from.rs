@@ -0,0 +1,108 @@
+use super::{date_range, today, Matcher, Operator};
+use crate::{Error, Result};
+use serde_json::{Map, Value};
+use std::convert::TryFrom;
+
+fn string_from_value(value: &Value, path: &str) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.to_owned()),
+        _ => Err(Error::invalid_query(path, "expected string")),
+    }
+}
+
+/// object of operators rather than a nested entry
+pub fn is_operator_object(object: &Map<String, Value>) -> bool {
+    !object.contains_key("_") && object.keys().any(|key| key.starts_with('$'))
+}
+
+pub fn matcher_from_object(object: &Map<String, Value>, path: &str) -> Result<Matcher> {
+    // the distance belongs to $fuzzy
+    if object.contains_key("$distance") && !object.contains_key("$fuzzy") {
+        return Err(Error::invalid_query(format!("{}.$distance", path), "expected $fuzzy"));
+    }
+
+    let operators = object
+        .iter()
+        .filter(|(key, _)| *key != "$distance")
+        .map(|(key, value)| {
+            let operator_path = format!("{}.{}", path, key);
+
+            let operand = || string_from_value(value, &operator_path);
+
+            match key.as_str() {
+                "$eq" => Ok(Operator::Eq(operand()?)),
+                "$regex" => Ok(Operator::Regex(operand()?)),
+                "$prefix" => Ok(Operator::Prefix(operand()?)),
+                "$gt" => Ok(Operator::Gt(operand()?)),
+                "$gte" => Ok(Operator::Gte(operand()?)),
+                "$lt" => Ok(Operator::Lt(operand()?)),
+                "$lte" => Ok(Operator::Lte(operand()?)),
+                "$in" => match value {
+                    Value::Array(vs) => Ok(Operator::In(
+                        vs.iter()
+                            .enumerate()
+                            .map(|(i, v)| string_from_value(v, &format!("{}[{}]", operator_path, i)))
+                            .collect::<Result<Vec<String>>>()?,
+                    )),
+                    _ => Err(Error::invalid_query(operator_path, "expected array")),
+                },
+                "$not" => match value {
+                    Value::String(s) => {
+                        Ok(Operator::Not(Matcher(vec![Operator::Regex(s.to_owned())])))
+                    }
+                    Value::Object(o) if is_operator_object(o) => {
+                        Ok(Operator::Not(matcher_from_object(o, &operator_path)?))
+                    }
+                    _ => Err(Error::invalid_query(operator_path, "expected string or operators")),
+                },
+                "$exists" => match value {
+                    Value::Bool(b) => Ok(Operator::Exists(*b)),
+                    _ => Err(Error::invalid_query(operator_path, "expected boolean")),
+                },
+                "$fold" => Ok(Operator::Fold(operand()?)),
+                "$fuzzy" => {
+                    let distance = match object.get("$distance") {
+                        None => None,
+                        Some(d) => match d.as_u64() {
+                            Some(n) => Some(n as usize),
+                            None => {
+                                return Err(Error::invalid_query(
+                                    format!("{}.$distance", path),
+                                    "expected non-negative integer",
+                                ))
+                            }
+                        },
+                    };
+
+                    Ok(Operator::Fuzzy(operand()?, distance))
+                }
+                "$date" => {
+                    let query = operand()?;
+
+                    if date_range(&query, today()).is_none() {
+                        return Err(Error::invalid_query(
+                            operator_path,
+                            "expected a date like 2001-05, a range like 2001-01..2001-06 or last 30 days",
+                        ));
+                    }
+
+                    Ok(Operator::Date(query))
+                }
+                _ => Err(Error::invalid_query(operator_path, "unknown operator")),
+            }
+        })
+        .collect::<Result<Vec<Operator>>>()?;
+
+    Ok(Matcher(operators))
+}
+
+impl TryFrom<Value> for Matcher {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self> {
+        match &value {
+            Value::Object(object) => matcher_from_object(object, "$"),
+            _ => Err(Error::invalid_query("$", "expected object")),
+        }
+    }
+}
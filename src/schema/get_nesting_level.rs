@@ -1,6 +1,6 @@
 use super::{Branch, Schema, Trunks};
 
-pub fn get_nesting_level(schema: &Schema, branch: &str) -> i32 {
+fn get_nesting_level_path(schema: &Schema, branch: &str, path: &mut Vec<String>) -> i32 {
     let trunks = match schema.0.get(branch) {
         None => vec![],
         Some(Branch {
@@ -8,12 +8,24 @@ pub fn get_nesting_level(schema: &Schema, branch: &str) -> i32 {
         }) => ts.to_vec(),
     };
 
-    let trunk_levels: Vec<i32> = trunks
-        .iter()
-        .map(|trunk| get_nesting_level(schema, trunk))
-        .collect();
+    path.push(branch.to_owned());
+
+    let mut level: i32 = -1;
+
+    for trunk in trunks.iter() {
+        // skip trunks that lead back into a cycle
+        if path.contains(trunk) {
+            continue;
+        }
+
+        level = level.max(get_nesting_level_path(schema, trunk, path));
+    }
 
-    let level: i32 = *trunk_levels.iter().max().unwrap_or(&-1);
+    path.pop();
 
     level + 1
 }
+
+pub fn get_nesting_level(schema: &Schema, branch: &str) -> i32 {
+    get_nesting_level_path(schema, branch, &mut vec![])
+}
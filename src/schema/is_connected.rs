@@ -1,18 +1,31 @@
 use super::{Branch, Leaves, Schema, Trunks};
+use std::collections::HashSet;
 
-pub fn is_connected(schema: &Schema, base: &str, branch: &str) -> bool {
+fn is_connected_visited(
+    schema: &Schema,
+    base: &str,
+    branch: &str,
+    visited: &mut HashSet<String>,
+) -> bool {
     if branch == base {
         // if branch is base, it is connected
         return true;
     }
 
+    // a trunk seen before leads into a cycle
+    if !visited.insert(branch.to_owned()) {
+        return false;
+    }
+
     let Branch {
         trunks: Trunks(trunks),
         leaves: Leaves(leaves),
+        ..
     } = match schema.0.get(branch) {
         None => Branch {
             trunks: Trunks(vec![]),
             leaves: Leaves(vec![]),
+            value_type: None,
         },
         Some(vs) => vs.clone(),
     };
@@ -23,7 +36,7 @@ pub fn is_connected(schema: &Schema, base: &str, branch: &str) -> bool {
             return true;
         }
 
-        if is_connected(schema, base, trunk) {
+        if is_connected_visited(schema, base, trunk, visited) {
             // if trunk is connected to base, leaf is also connected to base
             return true;
         }
@@ -32,3 +45,7 @@ pub fn is_connected(schema: &Schema, base: &str, branch: &str) -> bool {
     // if trunk is not connected to base, leaf is also not connected to base
     false
 }
+
+pub fn is_connected(schema: &Schema, base: &str, branch: &str) -> bool {
+    is_connected_visited(schema, base, branch, &mut HashSet::new())
+}
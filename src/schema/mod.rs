@@ -5,9 +5,16 @@ mod is_connected;
 mod sort_nesting_ascending;
 mod sort_nesting_descending;
 mod try_from;
+mod validate;
+mod validate_values;
+mod value_type;
+use crate::{Entry, Result};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::HashMap;
+pub use validate::SchemaProblem;
+pub use value_type::{parse_date, parse_datetime, ValueType};
+pub(crate) use value_type::{civil_from_days, days_from_civil, days_in_month, parse_digits};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Leaves(pub Vec<String>);
@@ -19,6 +26,9 @@ pub struct Trunks(pub Vec<String>);
 pub struct Branch {
     pub trunks: Trunks,
     pub leaves: Leaves,
+    /// declared in _-type.csv, values are strings otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_type: Option<ValueType>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -41,6 +51,25 @@ impl Schema {
         get_nesting_level::get_nesting_level(self, branch)
     }
 
+    pub fn validate(&self) -> Vec<SchemaProblem> {
+        validate::validate(self)
+    }
+
+    /// declared type of the branch, string if none
+    pub fn value_type(&self, branch: &str) -> ValueType {
+        match self.0.get(branch) {
+            Some(Branch {
+                value_type: Some(t), ..
+            }) => *t,
+            _ => ValueType::String,
+        }
+    }
+
+    /// fail on the first value of the entry that is not of the type of its branch
+    pub fn validate_values(&self, entry: &Entry) -> Result<()> {
+        validate_values::validate_values(self, entry)
+    }
+
     pub fn sort_nesting_descending(self) -> impl FnMut(&String, &String) -> Ordering {
         sort_nesting_descending::sort_nesting_descending(self)
     }
@@ -11,7 +11,7 @@ impl TryFrom<Entry> for Schema {
 
     fn try_from(entry: Entry) -> Result<Self> {
         if entry.base != "_" {
-            return Err(Error::from_message("base is not _"));
+            return Err(Error::invalid_query("$._", "base is not _"));
         }
 
         let node_map: HashMap<String, Branch> =
@@ -26,6 +26,7 @@ impl TryFrom<Entry> for Schema {
                                 None => Branch {
                                     trunks: Trunks(vec![]),
                                     leaves: Leaves(vec![]),
+                                    value_type: None,
                                 },
                                 Some(vs) => vs.clone(),
                             };
@@ -40,6 +41,7 @@ impl TryFrom<Entry> for Schema {
                                 Branch {
                                     trunks: trunk_trunks,
                                     leaves: trunk_leaves,
+                                    value_type: None,
                                 },
                             );
 
@@ -47,6 +49,7 @@ impl TryFrom<Entry> for Schema {
                                 None => Branch {
                                     trunks: Trunks(vec![]),
                                     leaves: Leaves(vec![]),
+                                    value_type: None,
                                 },
                                 Some(vs) => vs.clone(),
                             };
@@ -61,6 +64,7 @@ impl TryFrom<Entry> for Schema {
                                 Branch {
                                     trunks: leaf_trunks,
                                     leaves: leaf_leaves,
+                                    value_type: None,
                                 },
                             );
 
@@ -0,0 +1,144 @@
+use super::{Branch, Leaves, Schema, Trunks};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum SchemaProblem {
+    /// branches that lead back to the first branch
+    Cycle(Vec<String>),
+    /// branch is its own trunk
+    SelfLoop(String),
+    /// empty branch name next to a trunk or a leaf
+    EmptyName(String),
+    /// branch name can't be part of a trunk-leaf.csv filename
+    IllegalName(String),
+    /// branch has trunks but no root above them
+    Unreachable(String),
+}
+
+impl fmt::Display for SchemaProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaProblem::Cycle(branches) => write!(f, "cycle {}", branches.join(" -> ")),
+            SchemaProblem::SelfLoop(branch) => write!(f, "branch \"{}\" is its own trunk", branch),
+            SchemaProblem::EmptyName(branch) => {
+                write!(f, "empty branch name next to \"{}\"", branch)
+            }
+            SchemaProblem::IllegalName(branch) => {
+                write!(f, "illegal character in branch \"{}\"", branch)
+            }
+            SchemaProblem::Unreachable(branch) => {
+                write!(f, "branch \"{}\" is unreachable from any root", branch)
+            }
+        }
+    }
+}
+
+fn leaves_of(schema: &Schema, branch: &str) -> Vec<String> {
+    let mut leaves = match schema.0.get(branch) {
+        None => vec![],
+        Some(Branch {
+            leaves: Leaves(ls), ..
+        }) => ls.to_vec(),
+    };
+
+    leaves.sort();
+
+    leaves
+}
+
+fn find_cycles(
+    schema: &Schema,
+    branch: &str,
+    path: &mut Vec<String>,
+    visited: &mut HashSet<String>,
+    cycles: &mut Vec<Vec<String>>,
+) {
+    if let Some(i) = path.iter().position(|b| b == branch) {
+        // self-loops are reported separately
+        if path.len() - i > 1 {
+            cycles.push([&path[i..], &[branch.to_owned()]].concat());
+        }
+
+        return;
+    }
+
+    if visited.contains(branch) {
+        return;
+    }
+
+    visited.insert(branch.to_owned());
+
+    path.push(branch.to_owned());
+
+    for leaf in leaves_of(schema, branch) {
+        find_cycles(schema, &leaf, path, visited, cycles);
+    }
+
+    path.pop();
+}
+
+fn reach(schema: &Schema, branch: &str, reached: &mut HashSet<String>) {
+    if !reached.insert(branch.to_owned()) {
+        return;
+    }
+
+    for leaf in leaves_of(schema, branch) {
+        reach(schema, &leaf, reached);
+    }
+}
+
+pub fn validate(schema: &Schema) -> Vec<SchemaProblem> {
+    let mut branches: Vec<String> = schema.0.keys().cloned().collect();
+
+    branches.sort();
+
+    let mut problems = vec![];
+
+    for branch in branches.iter() {
+        if branch.is_empty() {
+            let mut neighbours = [
+                schema.0[branch].trunks.0.clone(),
+                schema.0[branch].leaves.0.clone(),
+            ]
+            .concat();
+
+            neighbours.sort();
+
+            for neighbour in neighbours {
+                problems.push(SchemaProblem::EmptyName(neighbour));
+            }
+        } else if branch.contains('-') || branch.contains('/') {
+            problems.push(SchemaProblem::IllegalName(branch.to_owned()));
+        }
+
+        if schema.0[branch].trunks.0.contains(branch) {
+            problems.push(SchemaProblem::SelfLoop(branch.to_owned()));
+        }
+    }
+
+    let mut cycles = vec![];
+
+    let mut visited = HashSet::new();
+
+    for branch in branches.iter() {
+        find_cycles(schema, branch, &mut vec![], &mut visited, &mut cycles);
+    }
+
+    for cycle in cycles {
+        problems.push(SchemaProblem::Cycle(cycle));
+    }
+
+    let mut reached = HashSet::new();
+
+    for branch in branches.iter().filter(|b| schema.0[*b].trunks.0.is_empty()) {
+        reach(schema, branch, &mut reached);
+    }
+
+    for branch in branches.iter().filter(|b| !reached.contains(*b)) {
+        problems.push(SchemaProblem::Unreachable(branch.to_owned()));
+    }
+
+    problems
+}
@@ -0,0 +1,29 @@
+use super::Schema;
+use crate::{Entry, Error, Result};
+
+pub fn validate_values(schema: &Schema, entry: &Entry) -> Result<()> {
+    // operators only select values, there is no value to write
+    if entry.base_matcher.is_some() {
+        return Err(Error::invalid_query(
+            format!("$.{}", entry.base),
+            "operators are only allowed in a query",
+        ));
+    }
+
+    let value_type = schema.value_type(&entry.base);
+
+    // an empty value stands for no value
+    if let Some(value) = entry.base_value.as_deref().filter(|v| !v.is_empty()) {
+        if !value_type.is_valid(value) {
+            return Err(Error::invalid_value(&entry.base, value, value_type));
+        }
+    }
+
+    for items in entry.leaves.values() {
+        for item in items {
+            validate_values(schema, item)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,295 @@
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// type that the values of a branch are declared with,
+/// values are still stored as strings in tablets
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueType {
+    Date,
+    Datetime,
+    Integer,
+    Decimal,
+    Boolean,
+    #[default]
+    String,
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ValueType::Date => "date",
+            ValueType::Datetime => "datetime",
+            ValueType::Integer => "integer",
+            ValueType::Decimal => "decimal",
+            ValueType::Boolean => "boolean",
+            ValueType::String => "string",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ValueType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "date" => Ok(ValueType::Date),
+            "datetime" => Ok(ValueType::Datetime),
+            "integer" => Ok(ValueType::Integer),
+            "decimal" => Ok(ValueType::Decimal),
+            "boolean" => Ok(ValueType::Boolean),
+            "string" => Ok(ValueType::String),
+            t => Err(Error::from_message(format!("unknown type {}", t))),
+        }
+    }
+}
+
+pub(crate) fn parse_digits(s: &str, len: usize) -> Option<i64> {
+    if s.len() != len || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    s.parse().ok()
+}
+
+fn is_leap(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub(crate) fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// days since 1970-01-01 of a proleptic gregorian date
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+
+    let era = year.div_euclid(400);
+
+    let year_of_era = year - era * 400;
+
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+// year, month and day of days since 1970-01-01
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+
+    let era = days.div_euclid(146097);
+
+    let day_of_era = days - era * 146097;
+
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+
+    let month_index = (5 * day_of_year + 2) / 153;
+
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// days since 1970-01-01 of a YYYY-MM-DD date
+pub fn parse_date(s: &str) -> Option<i64> {
+    let mut parts = s.split('-');
+
+    let year = parse_digits(parts.next()?, 4)?;
+
+    let month = parse_digits(parts.next()?, 2)?;
+
+    let day = parse_digits(parts.next()?, 2)?;
+
+    if parts.next().is_some() || !(1..=12).contains(&month) {
+        return None;
+    }
+
+    if !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+
+    Some(days_from_civil(year, month, day))
+}
+
+// seconds since midnight of HH:MM or HH:MM:SS with nanoseconds of a fraction
+fn parse_time(s: &str) -> Option<(i64, u32)> {
+    let (time, fraction) = match s.split_once('.') {
+        None => (s, None),
+        Some((t, f)) => (t, Some(f)),
+    };
+
+    let mut parts = time.split(':');
+
+    let hour = parse_digits(parts.next()?, 2)?;
+
+    let minute = parse_digits(parts.next()?, 2)?;
+
+    let second = match parts.next() {
+        None => 0,
+        Some(p) => parse_digits(p, 2)?,
+    };
+
+    if parts.next().is_some() || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let nanos = match fraction {
+        None => 0,
+        Some(f) if !f.is_empty() && f.len() <= 9 && f.bytes().all(|b| b.is_ascii_digit()) => {
+            format!("{:0<9}", f).parse().ok()?
+        }
+        Some(_) => return None,
+    };
+
+    Some((hour * 3600 + minute * 60 + second, nanos))
+}
+
+// seconds east of UTC of Z, +HH:MM or -HH:MM
+fn parse_offset(s: &str) -> Option<i64> {
+    if s == "Z" {
+        return Some(0);
+    }
+
+    let sign = match s.get(..1)? {
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+
+    let (hour, minute) = s.get(1..)?.split_once(':')?;
+
+    let hour = parse_digits(hour, 2)?;
+
+    let minute = parse_digits(minute, 2)?;
+
+    if hour > 23 || minute > 59 {
+        return None;
+    }
+
+    Some(sign * (hour * 3600 + minute * 60))
+}
+
+/// seconds since the epoch in UTC with nanoseconds of an ISO 8601 datetime,
+/// a datetime without an offset is read as UTC
+pub fn parse_datetime(s: &str) -> Option<(i64, u32)> {
+    let (date, rest) = s.split_at_checked(10)?;
+
+    let days = parse_date(date)?;
+
+    let rest = rest.strip_prefix('T').or_else(|| rest.strip_prefix(' '))?;
+
+    // the offset starts at Z, or at the last sign after the hour
+    let offset_start = rest
+        .char_indices()
+        .skip(2)
+        .find(|(_, c)| matches!(c, 'Z' | '+' | '-'))
+        .map(|(i, _)| i);
+
+    let (time, offset) = match offset_start {
+        None => (rest, 0),
+        Some(i) => (&rest[..i], parse_offset(&rest[i..])?),
+    };
+
+    let (seconds, nanos) = parse_time(time)?;
+
+    Some((days * 86400 + seconds - offset, nanos))
+}
+
+// digits with an optional sign and fraction, no exponent
+fn parse_decimal(s: &str) -> Option<f64> {
+    let unsigned = s.strip_prefix('-').unwrap_or(s);
+
+    let (whole, fraction) = match unsigned.split_once('.') {
+        None => (unsigned, "0"),
+        Some((w, f)) => (w, f),
+    };
+
+    let is_digits = |p: &str| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit());
+
+    if !is_digits(whole) || !is_digits(fraction) {
+        return None;
+    }
+
+    s.parse().ok()
+}
+
+fn parse_integer(s: &str) -> Option<i64> {
+    if s.starts_with('+') {
+        return None;
+    }
+
+    s.parse().ok()
+}
+
+fn parse_boolean(s: &str) -> Option<bool> {
+    match s {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+impl ValueType {
+    pub fn is_valid(&self, value: &str) -> bool {
+        match self {
+            ValueType::Date => parse_date(value).is_some(),
+            ValueType::Datetime => parse_datetime(value).is_some(),
+            ValueType::Integer => parse_integer(value).is_some(),
+            ValueType::Decimal => parse_decimal(value).is_some(),
+            ValueType::Boolean => parse_boolean(value).is_some(),
+            ValueType::String => true,
+        }
+    }
+
+    /// order of two values by the type, or as strings
+    /// when one of them is not a value of the type
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        let ordering = match self {
+            ValueType::Date => parse_date(a).zip(parse_date(b)).map(|(a, b)| a.cmp(&b)),
+            ValueType::Datetime => parse_datetime(a).zip(parse_datetime(b)).map(|(a, b)| a.cmp(&b)),
+            ValueType::Integer => parse_integer(a).zip(parse_integer(b)).map(|(a, b)| a.cmp(&b)),
+            ValueType::Decimal => parse_decimal(a)
+                .zip(parse_decimal(b))
+                .and_then(|(a, b)| a.partial_cmp(&b)),
+            ValueType::Boolean => parse_boolean(a).zip(parse_boolean(b)).map(|(a, b)| a.cmp(&b)),
+            ValueType::String => None,
+        };
+
+        ordering.unwrap_or_else(|| a.cmp(b))
+    }
+
+    /// json number or boolean of a value, or the string
+    /// when it is not a value of the type
+    pub fn to_value(&self, value: &str) -> Value {
+        let typed = match self {
+            ValueType::Integer => parse_integer(value).map(Value::from),
+            // keep the digits as written rather than round through a float
+            ValueType::Decimal => parse_decimal(value)
+                .and_then(|_| value.parse::<serde_json::Number>().ok())
+                .map(Value::Number),
+            ValueType::Boolean => parse_boolean(value).map(Value::from),
+            _ => None,
+        };
+
+        typed.unwrap_or_else(|| value.into())
+    }
+}
@@ -0,0 +1,88 @@
+extern crate dir_diff;
+use crate::{Dataset, Result};
+use assert_json_diff::assert_json_eq;
+use futures_util::pin_mut;
+use futures_util::stream::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use temp_dir::TempDir;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct CheckTest {
+    initial: String,
+    expected: Value,
+    fixed: String,
+    #[serde(default)]
+    quarantined: Vec<String>,
+}
+
+#[tokio::test]
+async fn check_test() -> Result<()> {
+    let file = fs::File::open("./src/test/cases/check.json").expect("file should open read only");
+
+    let tests: Vec<CheckTest> = serde_json::from_reader(file).expect("file should be proper JSON");
+
+    for test in tests.iter() {
+        let temp_path = TempDir::new()?;
+
+        let initial_path = format!("./src/test/datasets/{}", test.initial);
+
+        for file_entry in fs::read_dir(&initial_path)? {
+            let file_entry = file_entry?;
+
+            let file_type = file_entry.file_type()?;
+
+            if file_type.is_dir() {
+            } else {
+                fs::copy(
+                    file_entry.path(),
+                    temp_path.as_ref().join(file_entry.file_name()),
+                )?;
+            }
+        }
+
+        let fixed_str = format!("./src/test/datasets/{}", test.fixed);
+
+        let fixed_path = std::path::Path::new(&fixed_str);
+
+        let dataset = Dataset::new(&temp_path.path().to_owned());
+
+        let s = dataset.clone().check(true);
+
+        pin_mut!(s); // needed for iteration
+
+        let mut problems = vec![];
+
+        while let Some(problem) = s.next().await {
+            problems.push(problem?);
+        }
+
+        assert_json_eq!(serde_json::to_value(&problems)?, test.expected);
+
+        // unknown tablets are kept aside as they were
+        let quarantine_path = temp_path.path().join(".csvs").join("quarantine");
+
+        for filename in test.quarantined.iter() {
+            assert_eq!(
+                fs::read(quarantine_path.join(filename))?,
+                fs::read(std::path::Path::new(&initial_path).join(filename))?
+            );
+        }
+
+        if !test.quarantined.is_empty() {
+            fs::remove_dir_all(&quarantine_path)?;
+        }
+
+        assert!(!dir_diff::is_different(temp_path.path(), fixed_path)?);
+
+        // a fixed dataset has no problems
+        let s = dataset.check(false);
+
+        pin_mut!(s); // needed for iteration
+
+        assert!(s.next().await.is_none());
+    }
+
+    Ok(())
+}
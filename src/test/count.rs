@@ -0,0 +1,59 @@
+use crate::{Aggregation, Dataset, Entry, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct CountTest {
+    initial: String,
+    query: Vec<Value>,
+    count: usize,
+    aggregation: Option<Aggregation>,
+    #[serde(default)]
+    expected: Vec<(String, usize)>,
+    error: Option<String>,
+}
+
+#[tokio::test]
+async fn count_test() -> Result<()> {
+    let file = fs::File::open("./src/test/cases/count.json").expect("file should open read only");
+
+    let tests: Vec<CountTest> = serde_json::from_reader(file).expect("file should be proper JSON");
+
+    for test in tests.iter() {
+        let initial_path = format!("./src/test/datasets/{}", test.initial);
+
+        let dataset = Dataset::new(&std::path::Path::new(&initial_path).to_owned());
+
+        // parse query to Entry
+        let queries: Vec<Entry> = test
+            .query
+            .iter()
+            .map(|query| query.clone().try_into())
+            .collect::<Result<Vec<Entry>>>()?;
+
+        let count = dataset.clone().count(queries.clone()).await?;
+
+        assert_eq!(count, test.count);
+
+        let aggregation = match &test.aggregation {
+            None => continue,
+            Some(a) => a.clone(),
+        };
+
+        match dataset.aggregate(queries, aggregation).await {
+            Ok(counts) => {
+                assert_eq!(test.error, None, "expected aggregate error");
+
+                assert_eq!(counts, test.expected);
+            }
+            Err(e) => {
+                let expected = test.error.clone().expect("unexpected aggregate error");
+
+                assert!(e.to_string().starts_with(&expected), "{} is not {}", e, expected);
+            }
+        }
+    }
+
+    Ok(())
+}
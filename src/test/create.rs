@@ -0,0 +1,47 @@
+extern crate dir_diff;
+use crate::{Entry, Result, Dataset};
+use super::read_record;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use temp_dir::TempDir;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct CreateTest {
+    name: String,
+    schema: Option<String>,
+    expected: String,
+}
+
+#[tokio::test]
+async fn create_test() -> Result<()> {
+    let file = fs::File::open("./src/test/cases/create.json").expect("file should open read only");
+
+    let tests: Vec<CreateTest> = serde_json::from_reader(file).expect("file should be proper JSON");
+
+    for test in tests.iter() {
+        let temp_path = TempDir::new()?;
+
+        let expected_str = format!("./src/test/datasets/{}", test.expected);
+
+        let expected_path = std::path::Path::new(&expected_str);
+
+        // parse schema to Entry
+        let schema: Option<Entry> = match &test.schema {
+            None => None,
+            Some(s) => Some(read_record(s).try_into()?),
+        };
+
+        let dataset = Dataset::new(&temp_path.path().to_owned());
+
+        dataset.create(&test.name, schema.clone()).await?;
+
+        let created_path = temp_path.path().join(&test.name);
+
+        assert!(!dir_diff::is_different(&created_path, expected_path)?);
+
+        // refuse to overwrite an existing dataset
+        assert!(dataset.create(&test.name, schema).await.is_err());
+    }
+
+    Ok(())
+}
@@ -1,5 +1,5 @@
 extern crate dir_diff;
-use crate::{Entry, Result, Dataset};
+use crate::{Cascade, Entry, Result, Dataset};
 use super::read_record;
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -10,6 +10,9 @@ struct DeleteTest {
     initial: String,
     query: Vec<String>,
     expected: String,
+    #[serde(default)]
+    cascade: bool,
+    error: Option<String>,
 }
 
 #[tokio::test]
@@ -51,7 +54,20 @@ async fn delete_test() -> Result<()> {
 
         let dataset = Dataset::new(&temp_path.path().to_owned());
 
-        dataset.delete_record(queries).await;
+        let cascade = if test.cascade {
+            Cascade::Exclusive
+        } else {
+            Cascade::None
+        };
+
+        match dataset.delete_record(queries, cascade).await {
+            Ok(()) => assert_eq!(test.error, None, "expected delete error"),
+            Err(e) => {
+                let expected = test.error.clone().expect("unexpected delete error");
+
+                assert!(e.to_string().starts_with(&expected), "{} is not {}", e, expected);
+            }
+        }
 
         if dir_diff::is_different(temp_path.path(), expected_path)? {
             for file_entry in fs::read_dir(temp_path.path())? {
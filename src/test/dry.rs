@@ -0,0 +1,53 @@
+extern crate dir_diff;
+use crate::{Cascade, Changeset, Entry, Result, Dataset};
+use super::{copy_dir, read_record};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use temp_dir::TempDir;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct DryTest {
+    initial: String,
+    action: String,
+    query: Vec<String>,
+    expected: Changeset,
+}
+
+#[tokio::test]
+async fn dry_test() -> Result<()> {
+    let file = fs::File::open("./src/test/cases/dry.json").expect("file should open read only");
+
+    let tests: Vec<DryTest> = serde_json::from_reader(file).expect("file should be proper JSON");
+
+    for test in tests.iter() {
+        let temp_path = TempDir::new()?;
+
+        let initial_path = format!("./src/test/datasets/{}", test.initial);
+
+        copy_dir(Path::new(&initial_path), temp_path.path())?;
+
+        // parse query to Entry
+        let queries: Vec<Entry> = test
+            .query
+            .clone()
+            .into_iter()
+            .map(|query| read_record(&query).try_into())
+            .collect::<Result<Vec<Entry>>>()?;
+
+        let dataset = Dataset::new(&temp_path.path().to_owned());
+
+        let changeset = match test.action.as_str() {
+            "insert" => dataset.insert_record_dry(queries).await?,
+            "update" => dataset.update_record_dry(queries).await?,
+            _ => dataset.delete_record_dry(queries, Cascade::None).await?,
+        };
+
+        assert_eq!(changeset, test.expected);
+
+        // dry run leaves the dataset as it was
+        assert!(!dir_diff::is_different(temp_path.path(), &initial_path)?);
+    }
+
+    Ok(())
+}
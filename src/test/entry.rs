@@ -1,5 +1,5 @@
 use assert_json_diff::assert_json_eq;
-use csvs::{Entry, IntoValue, Result};
+use csvs::{Entry, ErrorKind, IntoValue, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs;
@@ -47,3 +47,32 @@ fn entry_into_test() -> Result<()> {
 
     Ok(())
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct EntryErrorTest {
+    value: Value,
+    path: String,
+}
+
+#[test]
+fn entry_error_test() -> Result<()> {
+    let file =
+        fs::File::open("./src/test/cases/entry_error.json").expect("file should open read only");
+
+    let tests: Vec<EntryErrorTest> =
+        serde_json::from_reader(file).expect("file should be proper JSON");
+
+    for test in tests.iter() {
+        let result: Result<Entry> = test.value.clone().try_into();
+
+        match result {
+            Ok(_) => panic!("expected error at {}", test.path),
+            Err(e) => match e.kind() {
+                ErrorKind::InvalidQuery { path, .. } => assert_eq!(path, &test.path),
+                kind => panic!("unexpected error {}", kind),
+            },
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use assert_json_diff::assert_json_eq;
+use serde_json::Value;
+extern crate dir_diff;
+use crate::{Entry, IntoValue, Result, Dataset};
+use super::{copy_dir, read_record};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use temp_dir::TempDir;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct IndexTest {
+    initial: String,
+    #[serde(default)]
+    insert: Vec<String>,
+    select: Vec<Value>,
+    selected: Vec<String>,
+    expected: String,
+    #[serde(default)]
+    text: bool,
+}
+
+#[tokio::test]
+async fn index_test() -> Result<()> {
+    let file = fs::File::open("./src/test/cases/index.json").expect("file should open read only");
+
+    let tests: Vec<IndexTest> = serde_json::from_reader(file).expect("file should be proper JSON");
+
+    for test in tests.iter() {
+        let temp_path = TempDir::new()?;
+
+        let initial_path = format!("./src/test/datasets/{}", test.initial);
+
+        copy_dir(Path::new(&initial_path), temp_path.path())?;
+
+        let expected_str = format!("./src/test/datasets/{}", test.expected);
+
+        let expected_path = std::path::Path::new(&expected_str);
+
+        let dataset = Dataset::new(&temp_path.path().to_owned());
+
+        if test.text {
+            dataset.clone().build_text_index(vec![])?;
+        } else {
+            dataset.clone().build_index(vec![])?;
+        }
+
+        // parse records to Entry
+        let records: Vec<Entry> = test
+            .insert
+            .iter()
+            .map(|record| read_record(record).try_into())
+            .collect::<Result<Vec<Entry>>>()?;
+
+        dataset.clone().insert_record(records).await?;
+
+        // parse query to Entry
+        let queries: Vec<Entry> = test
+            .select
+            .iter()
+            .map(|query| query.clone().try_into())
+            .collect::<Result<Vec<Entry>>>()?;
+
+        let entries = dataset.select_record(queries).await?;
+
+        let entries_json: Vec<Value> = entries.iter().map(|i| i.clone().into_value()).collect();
+
+        let selected_json: Vec<Value> = test
+            .selected
+            .iter()
+            .map(|record| read_record(record))
+            .collect();
+
+        assert_json_eq!(entries_json, selected_json);
+
+        if dir_diff::is_different(temp_path.path(), expected_path)? {
+            let index_path = Path::new(".csvs").join(if test.text { "text" } else { "index" });
+
+            for file_entry in fs::read_dir(expected_path.join(&index_path))? {
+                let file_entry = file_entry?;
+
+                let received = fs::read_to_string(temp_path.path().join(&index_path).join(file_entry.file_name()))?;
+
+                let expected = fs::read_to_string(file_entry.path())?;
+
+                assert_eq!(received, expected);
+            }
+        }
+
+        assert!(!dir_diff::is_different(temp_path.path(), expected_path)?);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn index_stale_test() -> Result<()> {
+    let temp_path = TempDir::new()?;
+
+    copy_dir(Path::new("./src/test/datasets/indexed"), temp_path.path())?;
+
+    let tablet_path = temp_path.path().join("datum-actdate.csv");
+
+    let index_modified = fs::metadata(temp_path.path().join(".csvs/index/datum-actdate.csv"))?.modified()?;
+
+    // an edit outside of csvs within the modification time of the index
+    let mut tablet = fs::OpenOptions::new().append(true).open(&tablet_path)?;
+
+    std::io::Write::write_all(&mut tablet, b"value3,2009-01-01\n")?;
+
+    tablet.set_modified(index_modified)?;
+
+    let dataset = Dataset::new(&temp_path.path().to_owned());
+
+    let query: Entry = serde_json::json!({ "_": "datum", "actdate": "^2009-01-01$" }).try_into()?;
+
+    let entries = dataset.select_record(vec![query]).await?;
+
+    let entries_json: Vec<Value> = entries.into_iter().map(|entry| entry.into_value()).collect();
+
+    assert_json_eq!(
+        entries_json,
+        vec![serde_json::json!({ "_": "datum", "datum": "value3", "actdate": "2009-01-01" })]
+    );
+
+    Ok(())
+}
+
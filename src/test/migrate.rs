@@ -0,0 +1,52 @@
+extern crate dir_diff;
+use super::copy_dir;
+use crate::{Dataset, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use temp_dir::TempDir;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct MigrateTest {
+    initial: String,
+    expected: String,
+}
+
+#[tokio::test]
+async fn migrate_test() -> Result<()> {
+    let file = fs::File::open("./src/test/cases/migrate.json").expect("file should open read only");
+
+    let tests: Vec<MigrateTest> = serde_json::from_reader(file).expect("file should be proper JSON");
+
+    for test in tests.iter() {
+        let temp_path = TempDir::new()?;
+
+        let initial_path = format!("./src/test/datasets/{}", test.initial);
+
+        copy_dir(Path::new(&initial_path), temp_path.path())?;
+
+        let expected_str = format!("./src/test/datasets/{}", test.expected);
+
+        let expected_path = std::path::Path::new(&expected_str);
+
+        let dataset = Dataset::migrate(&temp_path.path().to_owned()).await?;
+
+        assert_eq!(dataset.version().map(|v| v.to_string()), Some("0.0.2".to_owned()));
+
+        if dir_diff::is_different(temp_path.path(), expected_path)? {
+            for file_entry in fs::read_dir(temp_path.path())? {
+                let file_entry = file_entry?;
+
+                let received = fs::read_to_string(file_entry.path())?;
+
+                let expected = fs::read_to_string(expected_path.join(file_entry.file_name()))?;
+
+                assert_eq!(received, expected);
+            }
+        }
+
+        assert!(!dir_diff::is_different(temp_path.path(), expected_path)?);
+    }
+
+    Ok(())
+}
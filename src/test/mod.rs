@@ -1,15 +1,27 @@
+mod check;
+mod count;
+mod create;
 mod delete;
+mod dry;
 mod entry;
 mod grain;
+mod index;
 mod insert;
+mod migrate;
 mod mow;
+mod options;
 mod schema;
 mod select;
 mod sort;
 mod sow;
+mod transaction;
+mod types;
 mod update;
+mod version;
+use crate::Result;
 use serde_json::Value;
 use std::fs;
+use std::path::Path;
 
 pub fn read_record(path: &str) -> Value {
     let entry_path = format!("./src/test/records/{}.json", path);
@@ -21,3 +33,24 @@ pub fn read_record(path: &str) -> Value {
 
     entry_json
 }
+
+// datasets keep indexes and journals in .csvs, legacy datasets keep tablets in metadir
+pub fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    for file_entry in fs::read_dir(from)? {
+        let file_entry = file_entry?;
+
+        let file_type = file_entry.file_type()?;
+
+        let target = to.join(file_entry.file_name());
+
+        if file_type.is_dir() {
+            fs::create_dir_all(&target)?;
+
+            copy_dir(&file_entry.path(), &target)?;
+        } else {
+            fs::copy(file_entry.path(), target)?;
+        }
+    }
+
+    Ok(())
+}
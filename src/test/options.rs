@@ -0,0 +1,57 @@
+use crate::{Dataset, Result, ValueOption};
+use futures_util::pin_mut;
+use futures_util::stream::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct OptionsTest {
+    initial: String,
+    branch: String,
+    prefix: Option<String>,
+    #[serde(default)]
+    expected: Vec<ValueOption>,
+    error: Option<String>,
+}
+
+async fn select_options(dataset: Dataset, branch: &str, prefix: Option<&str>) -> Result<Vec<ValueOption>> {
+    let s = dataset.select_options(branch, prefix);
+
+    pin_mut!(s); // needed for iteration
+
+    let mut options = vec![];
+
+    while let Some(option) = s.next().await {
+        options.push(option?);
+    }
+
+    Ok(options)
+}
+
+#[tokio::test]
+async fn options_test() -> Result<()> {
+    let file = fs::File::open("./src/test/cases/options.json").expect("file should open read only");
+
+    let tests: Vec<OptionsTest> = serde_json::from_reader(file).expect("file should be proper JSON");
+
+    for test in tests.iter() {
+        let initial_path = format!("./src/test/datasets/{}", test.initial);
+
+        let dataset = Dataset::new(&std::path::Path::new(&initial_path).to_owned());
+
+        match select_options(dataset, &test.branch, test.prefix.as_deref()).await {
+            Ok(options) => {
+                assert_eq!(test.error, None, "expected options error");
+
+                assert_eq!(options, test.expected);
+            }
+            Err(e) => {
+                let expected = test.error.clone().expect("unexpected options error");
+
+                assert!(e.to_string().starts_with(&expected), "{} is not {}", e, expected);
+            }
+        }
+    }
+
+    Ok(())
+}
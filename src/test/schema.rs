@@ -1,5 +1,5 @@
 use assert_json_diff::assert_json_eq;
-use csvs::{Branch, Entry, Leaves, Result, Schema, Trunks};
+use csvs::{Branch, Entry, ErrorKind, Leaves, Result, Schema, SchemaProblem, Trunks};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
@@ -40,6 +40,7 @@ fn find_crown_test() {
             Branch {
                 trunks: Trunks(vec![]),
                 leaves: Leaves(vec!["date".to_owned(), "name".to_owned()]),
+                value_type: None,
             },
         ),
         (
@@ -47,6 +48,7 @@ fn find_crown_test() {
             Branch {
                 trunks: Trunks(vec!["datum".to_owned()]),
                 leaves: Leaves(vec![]),
+                value_type: None,
             },
         ),
         (
@@ -54,6 +56,7 @@ fn find_crown_test() {
             Branch {
                 trunks: Trunks(vec!["datum".to_owned()]),
                 leaves: Leaves(vec![]),
+                value_type: None,
             },
         ),
     ]));
@@ -64,3 +67,100 @@ fn find_crown_test() {
 
     assert_eq!(crown, vec!["date", "datum", "name"]);
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct ValidateTest {
+    schema: Value,
+    expected: Value,
+}
+
+#[test]
+fn validate_test() -> Result<()> {
+    let file =
+        fs::File::open("./src/test/cases/validate.json").expect("file should open read only");
+
+    let tests: Vec<ValidateTest> =
+        serde_json::from_reader(file).expect("file should be proper JSON");
+
+    for test in tests.iter() {
+        let schema: Schema = test.schema.clone().try_into()?;
+
+        let problems = schema.validate();
+
+        // cycles must not overflow the stack
+        schema.find_crown("datum");
+
+        schema.get_nesting_level("datum");
+
+        assert_json_eq!(serde_json::to_value(&problems)?, test.expected);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn schema_cache_test() -> Result<()> {
+    let temp_path = temp_dir::TempDir::new()?;
+
+    for file_entry in fs::read_dir("./src/test/datasets/default")? {
+        let file_entry = file_entry?;
+
+        fs::copy(file_entry.path(), temp_path.as_ref().join(file_entry.file_name()))?;
+    }
+
+    let dataset = csvs::Dataset::new(&temp_path.path().to_owned());
+
+    assert!(dataset.schema().await?.0.contains_key("datum"));
+
+    // an update of _ replaces the cached schema
+    let schema_new: Entry = serde_json::json!({ "_": "_", "event": ["actdate"] }).try_into()?;
+
+    dataset.clone().update_record(vec![schema_new]).await?;
+
+    let schema = dataset.schema().await?;
+
+    assert!(schema.0.contains_key("event"));
+
+    assert!(!schema.0.contains_key("datum"));
+
+    // so does an edit of _-_.csv
+    fs::write(temp_path.path().join("_-_.csv"), "datum,actdate\n")?;
+
+    let schema = dataset.schema().await?;
+
+    assert!(schema.0.contains_key("datum"));
+
+    assert!(!schema.0.contains_key("event"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_schema_test() -> Result<()> {
+    let temp_path = temp_dir::TempDir::new()?;
+
+    fs::write(temp_path.path().join("_-_.csv"), "datum,actname\na,b\nb,a\n")?;
+
+    // a dataset that was not opened still checks its schema
+    let dataset = csvs::Dataset::new(&temp_path.path().to_owned());
+
+    let error = dataset.schema().await.expect_err("expected schema error");
+
+    match error.kind() {
+        ErrorKind::SchemaCycle { branches, problems } => {
+            assert_eq!(branches, &vec!["a", "b", "a"]);
+
+            assert_eq!(
+                problems,
+                &vec![
+                    SchemaProblem::Cycle(vec!["a".to_owned(), "b".to_owned(), "a".to_owned()]),
+                    SchemaProblem::Unreachable("a".to_owned()),
+                    SchemaProblem::Unreachable("b".to_owned()),
+                ]
+            );
+        }
+        kind => panic!("unexpected error {}", kind),
+    }
+
+    Ok(())
+}
@@ -3,17 +3,40 @@ use serde_json::Value;
 extern crate dir_diff;
 use csvs::{
     Result,
-    Entry, IntoValue, Dataset
+    Cursor, Entry, IntoValue, Dataset, SelectOptions
 };
+use futures_util::pin_mut;
+use futures_util::stream::StreamExt;
 use super::read_record;
 use serde::{Deserialize, Serialize};
 use std::fs;
+use temp_dir::TempDir;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct SelectTest {
     initial: String,
     query: Vec<Value>,
+    #[serde(default)]
     expected: Vec<String>,
+    error: Option<String>,
+    #[serde(default)]
+    options: SelectOptions,
+}
+
+async fn select_options(dataset: Dataset, queries: Vec<Entry>, options: SelectOptions) -> Result<Vec<Entry>> {
+    let readable_stream = futures_util::stream::iter(queries.into_iter().map(Ok));
+
+    let s = dataset.select_record_stream(readable_stream, options);
+
+    pin_mut!(s); // needed for iteration
+
+    let mut entries = vec![];
+
+    while let Some(entry) = s.next().await {
+        entries.push(entry?);
+    }
+
+    Ok(entries)
 }
 
 #[tokio::test]
@@ -41,7 +64,20 @@ async fn select_test() -> Result<()> {
 
         let dataset = Dataset::new(&initial_path.to_owned());
 
-        let entries = dataset.select_record(queries).await?;
+        let entries = match select_options(dataset, queries, test.options.clone()).await {
+            Ok(entries) => {
+                assert_eq!(test.error, None, "expected select error");
+
+                entries
+            }
+            Err(e) => {
+                let expected = test.error.clone().expect("unexpected select error");
+
+                assert!(e.to_string().starts_with(&expected), "{} is not {}", e, expected);
+
+                continue;
+            }
+        };
 
         let entries_json: Vec<Value> = entries.iter().map(|i| i.clone().into_value()).collect();
 
@@ -59,3 +95,82 @@ async fn select_test() -> Result<()> {
 
     Ok(())
 }
+
+async fn select_page(dataset: Dataset, query: Entry, after: Option<Cursor>) -> Result<Vec<(Entry, Cursor)>> {
+    let options = SelectOptions {
+        limit: Some(1),
+        after,
+        ..Default::default()
+    };
+
+    let s = dataset.select_cursor_stream(futures_util::stream::iter(vec![Ok(query)]), options);
+
+    pin_mut!(s); // needed for iteration
+
+    let mut pages = vec![];
+
+    while let Some(page) = s.next().await {
+        pages.push(page?);
+    }
+
+    Ok(pages)
+}
+
+#[tokio::test]
+async fn select_cursor_test() -> Result<()> {
+    let temp_path = TempDir::new()?;
+
+    for file_entry in fs::read_dir("./src/test/datasets/default")? {
+        let file_entry = file_entry?;
+
+        if file_entry.file_type()?.is_file() {
+            fs::copy(file_entry.path(), temp_path.as_ref().join(file_entry.file_name()))?;
+        }
+    }
+
+    let dataset = Dataset::new(&temp_path.path().to_owned());
+
+    let query: Entry = read_record("record2001").try_into()?;
+
+    let query = Entry {
+        base_value: None,
+        base_matcher: None,
+        leaves: Default::default(),
+        ..query
+    };
+
+    let mut base_values = vec![];
+
+    let mut after = None;
+
+    loop {
+        let pages = select_page(dataset.clone(), query.clone(), after).await?;
+
+        let (entry, cursor) = match pages.into_iter().next() {
+            None => break,
+            Some(page) => page,
+        };
+
+        base_values.push(entry.base_value.clone().unwrap_or_default());
+
+        // an entry inserted before the cursor does not shift the next page
+        if base_values.len() == 2 {
+            let mut inserted = entry.clone();
+
+            inserted.base_value = Some("a".to_owned());
+
+            dataset.clone().insert_record(vec![inserted]).await?;
+        }
+
+        // a client only holds the opaque string
+        after = Some(cursor.to_string().parse()?);
+    }
+
+    assert_eq!(base_values, vec!["", "value1", "value2"]);
+
+    let entries = dataset.select_record(vec![query]).await?;
+
+    assert_eq!(entries.len(), 4, "entry should be inserted between pages");
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+extern crate dir_diff;
+use super::copy_dir;
+use crate::{Dataset, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use temp_dir::TempDir;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct TransactionTest {
+    initial: String,
+    expected: String,
+}
+
+#[tokio::test]
+async fn transaction_test() -> Result<()> {
+    let file = fs::File::open("./src/test/cases/transaction.json").expect("file should open read only");
+
+    let tests: Vec<TransactionTest> = serde_json::from_reader(file).expect("file should be proper JSON");
+
+    // opening replays or rolls back the interrupted commit, and so does a read
+    for (test, is_open) in tests.iter().flat_map(|test| [(test, true), (test, false)]) {
+        let temp_path = TempDir::new()?;
+
+        let initial_path = format!("./src/test/datasets/{}", test.initial);
+
+        copy_dir(Path::new(&initial_path), temp_path.path())?;
+
+        let expected_str = format!("./src/test/datasets/{}", test.expected);
+
+        let expected_path = std::path::Path::new(&expected_str);
+
+        if is_open {
+            Dataset::open(&temp_path.path().to_owned()).await?;
+        } else {
+            Dataset::new(&temp_path.path().to_owned()).schema().await?;
+        }
+
+        if dir_diff::is_different(temp_path.path(), expected_path)? {
+            for file_entry in fs::read_dir(expected_path)? {
+                let file_entry = file_entry?;
+
+                let received = fs::read_to_string(temp_path.path().join(file_entry.file_name()))?;
+
+                let expected = fs::read_to_string(file_entry.path())?;
+
+                assert_eq!(received, expected);
+            }
+        }
+
+        assert!(!dir_diff::is_different(temp_path.path(), expected_path)?);
+    }
+
+    Ok(())
+}
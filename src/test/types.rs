@@ -0,0 +1,81 @@
+extern crate dir_diff;
+use crate::{Dataset, Entry, IntoValue, Result, ValueType};
+use super::read_record;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use temp_dir::TempDir;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct TypeTest {
+    initial: String,
+    branch: String,
+    #[serde(rename = "type")]
+    value_type: Option<ValueType>,
+    expected: String,
+    error: Option<String>,
+}
+
+#[tokio::test]
+async fn type_test() -> Result<()> {
+    let file = fs::File::open("./src/test/cases/types.json").expect("file should open read only");
+
+    let tests: Vec<TypeTest> = serde_json::from_reader(file).expect("file should be proper JSON");
+
+    for test in tests.iter() {
+        let temp_path = TempDir::new()?;
+
+        let initial_path = format!("./src/test/datasets/{}", test.initial);
+
+        for file_entry in fs::read_dir(&initial_path)? {
+            let file_entry = file_entry?;
+
+            if file_entry.file_type()?.is_file() {
+                fs::copy(file_entry.path(), temp_path.as_ref().join(file_entry.file_name()))?;
+            }
+        }
+
+        let dataset = Dataset::new(&temp_path.path().to_owned());
+
+        match dataset.set_type(&test.branch, test.value_type).await {
+            Ok(()) => assert_eq!(test.error, None, "expected type error"),
+            Err(e) => {
+                let expected = test.error.clone().expect("unexpected type error");
+
+                assert!(e.to_string().starts_with(&expected), "{} is not {}", e, expected);
+            }
+        }
+
+        let expected_path = format!("./src/test/datasets/{}", test.expected);
+
+        assert!(!dir_diff::is_different(temp_path.path(), expected_path)?);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn typed_value_test() -> Result<()> {
+    let dataset = Dataset::new(&std::path::Path::new("./src/test/datasets/typed").to_owned());
+
+    let schema = dataset.schema().await?;
+
+    let entry: Entry = read_record("record_typed_1").try_into()?;
+
+    assert_eq!(entry.clone().into_typed_value(&schema), read_record("record_typed_1_value"));
+
+    // typed json reads back to the stored values
+    let entry_typed: Entry = read_record("record_typed_1_value").try_into()?;
+
+    assert_eq!(entry_typed.into_value(), entry.into_value());
+
+    // a value that is not of the type stays a string
+    let entry: Entry = json!({ "_": "datum", "datum": "value4", "amount": "one" }).try_into()?;
+
+    assert_eq!(
+        entry.into_typed_value(&schema),
+        json!({ "_": "datum", "datum": "value4", "amount": "one" })
+    );
+
+    Ok(())
+}
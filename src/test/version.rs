@@ -0,0 +1,50 @@
+use crate::{Dataset, Result};
+use csvs::VersionError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct VersionTest {
+    initial: String,
+    expected: Option<String>,
+    error: Option<String>,
+}
+
+#[tokio::test]
+async fn version_test() -> Result<()> {
+    let file = fs::File::open("./src/test/cases/version.json").expect("file should open read only");
+
+    let tests: Vec<VersionTest> = serde_json::from_reader(file).expect("file should be proper JSON");
+
+    for test in tests.iter() {
+        let initial_path = format!("./src/test/datasets/{}", test.initial);
+
+        let initial_path = std::path::Path::new(&initial_path);
+
+        match Dataset::open(&initial_path.to_owned()).await {
+            Ok(dataset) => {
+                assert_eq!(dataset.version().map(|v| v.to_string()), test.expected);
+            }
+            Err(e) => {
+                let kind = match e.downcast_ref::<VersionError>() {
+                    None => panic!("unexpected error {}", e),
+                    Some(v) => format!("{:?}", v),
+                };
+
+                let expected = test.error.clone().expect("unexpected version error");
+
+                assert!(kind.starts_with(&expected), "{} is not {}", kind, expected);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn version_new_test() {
+    // a dataset that was not opened has not read .csvs.csv
+    let dataset = Dataset::new(&std::path::Path::new("./src/test/datasets/default").to_owned());
+
+    assert_eq!(dataset.version(), None);
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+
+/// version of the dataset format written by this crate
+pub const CSVS_VERSION: Version = Version {
+    major: 0,
+    minor: 0,
+    patch: 2,
+};
+
+/// oldest version that this crate reads as it is
+pub const FORMAT_VERSION: Version = Version {
+    major: 0,
+    minor: 0,
+    patch: 2,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+#[derive(Debug)]
+pub enum VersionError {
+    /// dataset has no .csvs.csv, likely a legacy layout
+    Missing,
+    /// .csvs.csv does not name a csvs version
+    Malformed(String),
+    /// dataset was written by an older csvs and needs migration
+    Outdated(Version),
+    /// dataset was written by a newer csvs
+    TooNew(Version),
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl TryFrom<&str> for Version {
+    type Error = VersionError;
+
+    fn try_from(value: &str) -> std::result::Result<Self, VersionError> {
+        let parts = value
+            .trim()
+            .split('.')
+            .map(|part| part.parse::<u32>())
+            .collect::<std::result::Result<Vec<u32>, _>>()
+            .map_err(|_| VersionError::Malformed(value.to_owned()))?;
+
+        match parts[..] {
+            [major, minor, patch] => Ok(Version {
+                major,
+                minor,
+                patch,
+            }),
+            _ => Err(VersionError::Malformed(value.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for VersionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VersionError::Missing => write!(f, "missing .csvs.csv, dataset might need migration"),
+            VersionError::Malformed(s) => write!(f, "unknown dataset version \"{}\"", s),
+            VersionError::Outdated(v) => write!(
+                f,
+                "dataset version {} is older than {}, dataset needs migration",
+                v, FORMAT_VERSION
+            ),
+            VersionError::TooNew(v) => write!(
+                f,
+                "dataset version {} is newer than supported {}",
+                v, CSVS_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VersionError {}
+
+/// read the version marker from .csvs.csv in the dataset directory
+pub fn read_version(dir: &Path) -> std::result::Result<Version, VersionError> {
+    let file = match File::open(dir.join(".csvs.csv")) {
+        Err(_) => return Err(VersionError::Missing),
+        Ok(f) => f,
+    };
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(file);
+
+    let record = match rdr.records().next() {
+        Some(Ok(r)) => r,
+        _ => return Err(VersionError::Malformed("".to_owned())),
+    };
+
+    match (record.get(0), record.get(1)) {
+        (Some("csvs"), Some(v)) => v.try_into(),
+        _ => Err(VersionError::Malformed(record.iter().collect::<Vec<&str>>().join(","))),
+    }
+}
+
+/// write a .csvs.csv with the version of this crate
+pub fn write_version(filepath: &Path) -> crate::Result<()> {
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_path(filepath)?;
+
+    wtr.write_record(["csvs", &CSVS_VERSION.to_string()])?;
+
+    wtr.flush()?;
+
+    Ok(())
+}
+
+/// check that this crate can read a dataset of the given version,
+/// patch releases share the format of their major.minor
+pub fn check_version(version: Version) -> std::result::Result<Version, VersionError> {
+    if version < FORMAT_VERSION {
+        return Err(VersionError::Outdated(version));
+    }
+
+    let release = (version.major, version.minor);
+
+    match release.cmp(&(CSVS_VERSION.major, CSVS_VERSION.minor)) {
+        Ordering::Greater => Err(VersionError::TooNew(version)),
+        _ => Ok(version),
+    }
+}